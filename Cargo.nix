@@ -2509,7 +2509,7 @@ rec {
           "server" = [ "dep:httpdate" "dep:pin-project-lite" "dep:smallvec" ];
           "tracing" = [ "dep:tracing" ];
         };
-        resolvedDefaultFeatures = [ "client" "default" "http1" ];
+        resolvedDefaultFeatures = [ "client" "default" "http1" "server" ];
       };
       "hyper-http-proxy" = rec {
         crateName = "hyper-http-proxy";
@@ -3336,7 +3336,7 @@ rec {
           "unstable-runtime" = [ "kube-runtime/unstable-runtime" ];
           "ws" = [ "kube-client/ws" "kube-core/ws" ];
         };
-        resolvedDefaultFeatures = [ "client" "config" "derive" "jsonpatch" "kube-client" "kube-derive" "kube-runtime" "runtime" "rustls-tls" ];
+        resolvedDefaultFeatures = [ "client" "config" "derive" "jsonpatch" "kube-client" "kube-derive" "kube-runtime" "runtime" "rustls-tls" "unstable-runtime" ];
       };
       "kube-client" = rec {
         crateName = "kube-client";
@@ -3809,6 +3809,7 @@ rec {
         features = {
           "unstable-runtime" = [ "unstable-runtime-subscribe" "unstable-runtime-predicates" "unstable-runtime-stream-control" "unstable-runtime-reconcile-on" ];
         };
+        resolvedDefaultFeatures = [ "unstable-runtime" "unstable-runtime-predicates" "unstable-runtime-reconcile-on" "unstable-runtime-stream-control" "unstable-runtime-subscribe" ];
       };
       "lazy_static" = rec {
         crateName = "lazy_static";
@@ -5001,6 +5002,55 @@ rec {
         ];
 
       };
+      "prometheus" = rec {
+        crateName = "prometheus";
+        version = "0.13.4";
+        edition = "2018";
+        sha256 = "1lbymqdsh9v4zk4fjdq2gq6lbxspp1w3z2b9vfb7y7vp625c4crx";
+        authors = [
+          "overvenus@gmail.com"
+          "siddontang@gmail.com"
+          "vistaswx@gmail.com"
+        ];
+        dependencies = [
+          {
+            name = "cfg-if";
+            packageId = "cfg-if";
+          }
+          {
+            name = "fnv";
+            packageId = "fnv";
+          }
+          {
+            name = "lazy_static";
+            packageId = "lazy_static";
+          }
+          {
+            name = "memchr";
+            packageId = "memchr";
+          }
+          {
+            name = "parking_lot";
+            packageId = "parking_lot";
+          }
+          {
+            name = "thiserror";
+            packageId = "thiserror";
+          }
+        ];
+        features = {
+          "default" = [ "protobuf" ];
+          "gen" = [ "protobuf-codegen-pure" ];
+          "libc" = [ "dep:libc" ];
+          "nightly" = [ "libc" ];
+          "process" = [ "libc" "procfs" ];
+          "procfs" = [ "dep:procfs" ];
+          "protobuf" = [ "dep:protobuf" ];
+          "protobuf-codegen-pure" = [ "dep:protobuf-codegen-pure" ];
+          "push" = [ "reqwest" "libc" "protobuf" ];
+          "reqwest" = [ "dep:reqwest" ];
+        };
+      };
       "quote" = rec {
         crateName = "quote";
         version = "1.0.37";
@@ -6453,6 +6503,26 @@ rec {
             packageId = "futures 0.3.31";
             features = [ "compat" ];
           }
+          {
+            name = "http-body-util";
+            packageId = "http-body-util";
+          }
+          {
+            name = "hyper";
+            packageId = "hyper";
+            features = [ "client" "http1" "server" ];
+          }
+          {
+            name = "hyper-util";
+            packageId = "hyper-util";
+            features = [ "tokio" ];
+          }
+          {
+            name = "kube";
+            packageId = "kube";
+            usesDefaultFeatures = false;
+            features = [ "unstable-runtime" ];
+          }
           {
             name = "pin-project";
             packageId = "pin-project";
@@ -6461,6 +6531,15 @@ rec {
             name = "product-config";
             packageId = "product-config";
           }
+          {
+            name = "prometheus";
+            packageId = "prometheus";
+            usesDefaultFeatures = false;
+          }
+          {
+            name = "rustls-pemfile";
+            packageId = "rustls-pemfile";
+          }
           {
             name = "semver";
             packageId = "semver";
@@ -6474,6 +6553,10 @@ rec {
             name = "serde_json";
             packageId = "serde_json";
           }
+          {
+            name = "serde_yaml";
+            packageId = "serde_yaml";
+          }
           {
             name = "snafu";
             packageId = "snafu 0.8.5";
@@ -6492,11 +6575,27 @@ rec {
             packageId = "tokio";
             features = [ "full" ];
           }
+          {
+            name = "tokio-rustls";
+            packageId = "tokio-rustls";
+            usesDefaultFeatures = false;
+            features = [ "logging" "ring" "tls12" ];
+          }
           {
             name = "tracing";
             packageId = "tracing";
           }
         ];
+        devDependencies = [
+          {
+            name = "regex";
+            packageId = "regex";
+          }
+          {
+            name = "xml-rs";
+            packageId = "xml-rs";
+          }
+        ];
         buildDependencies = [
           {
             name = "built";
@@ -6504,12 +6603,6 @@ rec {
             features = [ "chrono" "git2" ];
           }
         ];
-        devDependencies = [
-          {
-            name = "serde_yaml";
-            packageId = "serde_yaml";
-          }
-        ];
 
       };
       "stackable-operator" = rec {
@@ -7295,7 +7388,7 @@ rec {
           "ring" = [ "rustls/ring" ];
           "tls12" = [ "rustls/tls12" ];
         };
-        resolvedDefaultFeatures = [ "logging" "tls12" ];
+        resolvedDefaultFeatures = [ "logging" "ring" "tls12" ];
       };
       "tokio-stream" = rec {
         crateName = "tokio-stream";
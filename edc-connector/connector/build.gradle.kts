@@ -86,6 +86,12 @@ dependencies {
 
 	//Logging
 	implementation("${edcGroup}:monitor-jdk-logger:${edcVersion}")
+
+	//Metrics, exported by the JMX exporter javaagent
+	implementation("${edcGroup}:micrometer-core:${edcVersion}")
+	implementation("${edcGroup}:jersey-micrometer:${edcVersion}")
+	implementation("${edcGroup}:jetty-micrometer:${edcVersion}")
+	implementation(project(":edc-ionos-extension:metrics-jmx"))
 }
 
 repositories {
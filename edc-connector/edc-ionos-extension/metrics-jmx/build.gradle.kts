@@ -0,0 +1,28 @@
+/*
+ *  Copyright (c) 2024 Stackable GmbH
+ *
+ *  This program and the accompanying materials are made available under the
+ *  terms of the Apache License, Version 2.0 which is available at
+ *  https://www.apache.org/licenses/LICENSE-2.0
+ *
+ *  SPDX-License-Identifier: Apache-2.0
+ *
+ *  Contributors:
+ *       Stackable GmbH - Initial API and Implementation
+ *
+ */
+plugins {
+    `java-library`
+}
+
+val edcGroup: String by project
+val edcVersion: String by project
+val metaModelVersion: String by project
+val micrometerVersion: String by project
+
+dependencies {
+    api("${edcGroup}:runtime-metamodel:${metaModelVersion}")
+    api("${edcGroup}:core-spi:${edcVersion}")
+
+    implementation("io.micrometer:micrometer-registry-jmx:${micrometerVersion}")
+}
@@ -0,0 +1,54 @@
+/*
+ *  Copyright (c) 2024 Stackable GmbH
+ *
+ *  This program and the accompanying materials are made available under the
+ *  terms of the Apache License, Version 2.0 which is available at
+ *  https://www.apache.org/licenses/LICENSE-2.0
+ *
+ *  SPDX-License-Identifier: Apache-2.0
+ *
+ *  Contributors:
+ *       Stackable GmbH - Initial API and Implementation
+ *
+ */
+
+package com.ionos.edc.extension.metrics;
+
+import io.micrometer.core.instrument.Clock;
+import io.micrometer.core.instrument.Metrics;
+import io.micrometer.jmx.JmxConfig;
+import io.micrometer.jmx.JmxMeterRegistry;
+import org.eclipse.edc.runtime.metamodel.annotation.Extension;
+import org.eclipse.edc.spi.system.ServiceExtension;
+import org.eclipse.edc.spi.system.ServiceExtensionContext;
+
+/**
+ * Publishes the Micrometer metrics of the EDC, e.g. of the HTTP requests to negotiate contracts
+ * and to transfer data, as MBeans, so that the JMX exporter javaagent can export them to
+ * Prometheus.
+ */
+@Extension(value = JmxMetricsExtension.NAME)
+public class JmxMetricsExtension implements ServiceExtension {
+    public static final String NAME = "JMX Metrics";
+
+    private JmxMeterRegistry registry;
+
+    @Override
+    public String name() {
+        return NAME;
+    }
+
+    @Override
+    public void initialize(ServiceExtensionContext context) {
+        // The EDC registers its meters in the global registry, which only forwards them to the
+        // registries added to it
+        registry = new JmxMeterRegistry(JmxConfig.DEFAULT, Clock.SYSTEM);
+        Metrics.addRegistry(registry);
+    }
+
+    @Override
+    public void shutdown() {
+        Metrics.removeRegistry(registry);
+        registry.close();
+    }
+}
@@ -35,6 +35,8 @@ include(":edc-ionos-extension:core-ionos-s3")
 
 include(":edc-ionos-extension:vault-hashicorp")
 
+include(":edc-ionos-extension:metrics-jmx")
+
 include(":connector")
 
 include(":example:file-transfer-push-daps:transfer-file")
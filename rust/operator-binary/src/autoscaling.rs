@@ -0,0 +1,317 @@
+//! HorizontalPodAutoscalers which scale the StatefulSets of the rolegroups with `autoscaling`
+use snafu::{ResultExt, Snafu};
+use stackable_operator::{
+    builder::meta::ObjectMetaBuilder,
+    client::Client,
+    commons::product_image_selection::ResolvedProductImage,
+    k8s_openapi::{
+        api::{
+            apps::v1::StatefulSet,
+            autoscaling::v2::{
+                CrossVersionObjectReference, HorizontalPodAutoscaler, HorizontalPodAutoscalerSpec,
+                MetricIdentifier, MetricSpec, MetricTarget, PodsMetricSource, ResourceMetricSource,
+            },
+        },
+        apimachinery::pkg::apis::meta::v1::LabelSelector,
+    },
+    kube::ResourceExt,
+    kvp::{LabelError, Labels},
+    role_utils::RoleGroupRef,
+};
+
+use crate::{
+    controller::{build_recommended_labels, EDC_CONTROLLER_NAME},
+    crd::{AutoscalingConfig, AutoscalingTarget, EDCCluster, EDCRole, APP_NAME},
+};
+
+#[derive(Snafu, Debug)]
+pub enum Error {
+    #[snafu(display("object is missing metadata to build owner reference"))]
+    ObjectMissingMetadataForOwnerRef {
+        source: stackable_operator::builder::meta::Error,
+    },
+    #[snafu(display("failed to build object meta data"))]
+    ObjectMeta {
+        source: stackable_operator::builder::meta::Error,
+    },
+    #[snafu(display("failed to build the label selector of the HorizontalPodAutoscalers"))]
+    BuildLabel { source: LabelError },
+    #[snafu(display("failed to list the HorizontalPodAutoscalers"))]
+    ListHpas {
+        source: stackable_operator::client::Error,
+    },
+    #[snafu(display("failed to apply the HorizontalPodAutoscaler [{name}]"))]
+    ApplyHpa {
+        source: stackable_operator::client::Error,
+        name: String,
+    },
+    #[snafu(display("failed to delete the orphaned HorizontalPodAutoscaler [{name}]"))]
+    DeleteHpa {
+        source: stackable_operator::client::Error,
+        name: String,
+    },
+}
+
+type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// Build the [`HorizontalPodAutoscaler`] of the given rolegroup, which is named after the
+/// StatefulSet it scales
+pub fn build_rolegroup_hpa(
+    edc: &EDCCluster,
+    resolved_product_image: &ResolvedProductImage,
+    rolegroup_ref: &RoleGroupRef<EDCCluster>,
+    autoscaling: &AutoscalingConfig,
+) -> Result<HorizontalPodAutoscaler> {
+    let metadata = ObjectMetaBuilder::new()
+        .name_and_namespace(edc)
+        .name(rolegroup_ref.object_name())
+        .ownerreference_from_resource(edc, None, Some(true))
+        .context(ObjectMissingMetadataForOwnerRefSnafu)?
+        .with_recommended_labels(build_recommended_labels(
+            edc,
+            &resolved_product_image.app_version_label,
+            &rolegroup_ref.role,
+            &rolegroup_ref.role_group,
+        ))
+        .context(ObjectMetaSnafu)?
+        .build();
+
+    let metric = match &autoscaling.target {
+        AutoscalingTarget::CpuUtilization(utilization) => MetricSpec {
+            type_: "Resource".to_string(),
+            resource: Some(ResourceMetricSource {
+                name: "cpu".to_string(),
+                target: MetricTarget {
+                    type_: "Utilization".to_string(),
+                    average_utilization: Some((*utilization).into()),
+                    ..MetricTarget::default()
+                },
+            }),
+            ..MetricSpec::default()
+        },
+        AutoscalingTarget::PodsMetric(pods_metric) => MetricSpec {
+            type_: "Pods".to_string(),
+            pods: Some(PodsMetricSource {
+                metric: MetricIdentifier {
+                    name: pods_metric.name.clone(),
+                    selector: None,
+                },
+                target: MetricTarget {
+                    type_: "AverageValue".to_string(),
+                    average_value: Some(pods_metric.average_value.clone()),
+                    ..MetricTarget::default()
+                },
+            }),
+            ..MetricSpec::default()
+        },
+    };
+
+    Ok(HorizontalPodAutoscaler {
+        metadata,
+        spec: Some(HorizontalPodAutoscalerSpec {
+            scale_target_ref: CrossVersionObjectReference {
+                api_version: Some("apps/v1".to_string()),
+                kind: "StatefulSet".to_string(),
+                name: rolegroup_ref.object_name(),
+            },
+            min_replicas: Some(autoscaling.min_replicas.into()),
+            max_replicas: autoscaling.max_replicas.into(),
+            metrics: Some(vec![metric]),
+            behavior: None,
+        }),
+        status: None,
+    })
+}
+
+/// The replicas of the applied StatefulSet of an autoscaled rolegroup
+///
+/// The operator gives up the replicas only when the HorizontalPodAutoscaler owns them, i.e. when
+/// it scaled the StatefulSet via the scale subresource. Before, the current replicas are kept,
+/// because Kubernetes would otherwise reset the replicas of the StatefulSet to 1, see
+/// <https://kubernetes.io/docs/tasks/run-application/horizontal-pod-autoscale/#migrating-deployments-and-statefulsets-to-horizontal-autoscaling>.
+pub fn replicas_until_hpa_handover(
+    previous_statefulset: Option<&StatefulSet>,
+    autoscaling: &AutoscalingConfig,
+) -> Option<i32> {
+    match previous_statefulset {
+        Some(statefulset) if hpa_owns_replicas(statefulset) => None,
+        Some(statefulset) => statefulset
+            .spec
+            .as_ref()
+            .and_then(|spec| spec.replicas)
+            .or(Some(autoscaling.min_replicas.into())),
+        None => Some(autoscaling.min_replicas.into()),
+    }
+}
+
+/// Whether the replicas of the given StatefulSet are managed via the scale subresource
+fn hpa_owns_replicas(statefulset: &StatefulSet) -> bool {
+    statefulset
+        .metadata
+        .managed_fields
+        .iter()
+        .flatten()
+        .filter(|entry| entry.subresource.as_deref() == Some("scale"))
+        .filter_map(|entry| entry.fields_v1.as_ref())
+        .any(|fields| fields.0.pointer("/f:spec/f:replicas").is_some())
+}
+
+/// Apply the given HorizontalPodAutoscaler
+///
+/// It is applied before the StatefulSet it scales, so that the StatefulSet gives up its replicas
+/// only after the HorizontalPodAutoscaler took them over.
+pub async fn apply_hpa(client: &Client, hpa: &HorizontalPodAutoscaler) -> Result<()> {
+    client
+        .apply_patch(EDC_CONTROLLER_NAME, hpa, hpa)
+        .await
+        .with_context(|_| ApplyHpaSnafu {
+            name: hpa.name_any(),
+        })?;
+
+    Ok(())
+}
+
+/// Delete the HorizontalPodAutoscalers of the EDCCluster which are not in the given ones, e.g.
+/// the ones of removed rolegroups or of rolegroups without `autoscaling`
+///
+/// HorizontalPodAutoscalers are not managed by the ClusterResources, so that their orphans are
+/// deleted here.
+pub async fn delete_orphaned_hpas(
+    client: &Client,
+    edc: &EDCCluster,
+    namespace: &str,
+    hpas: &[HorizontalPodAutoscaler],
+) -> Result<()> {
+    let selector = LabelSelector {
+        match_labels: Some(
+            Labels::role_selector(edc, APP_NAME, &EDCRole::Connector.to_string())
+                .context(BuildLabelSnafu)?
+                .into(),
+        ),
+        ..LabelSelector::default()
+    };
+    let existing_hpas = client
+        .list_with_label_selector::<HorizontalPodAutoscaler>(namespace, &selector)
+        .await
+        .context(ListHpasSnafu)?;
+
+    for orphan in existing_hpas
+        .iter()
+        .filter(|existing| !hpas.iter().any(|hpa| hpa.name_any() == existing.name_any()))
+    {
+        client
+            .delete(orphan)
+            .await
+            .with_context(|_| DeleteHpaSnafu {
+                name: orphan.name_any(),
+            })?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use stackable_operator::k8s_openapi::apimachinery::pkg::api::resource::Quantity;
+
+    use super::*;
+    use crate::{controller::DOCKER_IMAGE_BASE_NAME, test_fixtures::edc_cluster};
+
+    fn build_hpa(autoscaling: &str) -> HorizontalPodAutoscalerSpec {
+        let edc = edc_cluster(
+            "
+            roleGroups:
+              default: {}
+            ",
+        );
+        let autoscaling: AutoscalingConfig =
+            serde_yaml::from_str(autoscaling).expect("invalid test input");
+        let resolved_product_image = edc.spec.image.resolve(DOCKER_IMAGE_BASE_NAME, "0.0.0-dev");
+
+        build_rolegroup_hpa(
+            &edc,
+            &resolved_product_image,
+            &edc.server_rolegroup_ref("default"),
+            &autoscaling,
+        )
+        .expect("failed to build the HorizontalPodAutoscaler")
+        .spec
+        .unwrap()
+    }
+
+    #[test]
+    fn test_cpu_utilization() {
+        let spec = build_hpa("maxReplicas: 5");
+
+        assert_eq!("provider-server-default", spec.scale_target_ref.name);
+        assert_eq!("StatefulSet", spec.scale_target_ref.kind);
+        assert_eq!(Some(1), spec.min_replicas);
+        assert_eq!(5, spec.max_replicas);
+
+        let metrics = spec.metrics.unwrap();
+        let resource = metrics[0].resource.as_ref().unwrap();
+        assert_eq!("cpu", resource.name);
+        assert_eq!(Some(80), resource.target.average_utilization);
+    }
+
+    #[test]
+    fn test_pods_metric() {
+        let spec = build_hpa(
+            "
+            minReplicas: 2
+            maxReplicas: 10
+            target:
+              podsMetric:
+                name: edc_active_transfers
+                averageValue: '20'
+            ",
+        );
+
+        assert_eq!(Some(2), spec.min_replicas);
+
+        let metrics = spec.metrics.unwrap();
+        let pods = metrics[0].pods.as_ref().unwrap();
+        assert_eq!("Pods", metrics[0].type_);
+        assert_eq!("edc_active_transfers", pods.metric.name);
+        assert_eq!(Some(Quantity("20".to_string())), pods.target.average_value);
+    }
+
+    #[test]
+    fn test_replicas_until_hpa_handover() {
+        let autoscaling: AutoscalingConfig =
+            serde_yaml::from_str("{minReplicas: 2, maxReplicas: 5}").expect("invalid test input");
+        let statefulset = |managed_fields: &str| -> StatefulSet {
+            serde_yaml::from_str(&format!(
+                "
+                metadata:
+                  name: provider-server-default
+                  managedFields: {managed_fields}
+                spec:
+                  replicas: 3
+                  selector: {{}}
+                  serviceName: provider-server-default
+                  template: {{}}
+                "
+            ))
+            .expect("invalid test input")
+        };
+
+        assert_eq!(Some(2), replicas_until_hpa_handover(None, &autoscaling));
+
+        let applied = statefulset(
+            r#"[{manager: edccluster, operation: Apply, fieldsV1: {"f:spec": {"f:replicas": {}}}}]"#,
+        );
+        assert_eq!(
+            Some(3),
+            replicas_until_hpa_handover(Some(&applied), &autoscaling)
+        );
+
+        let scaled = statefulset(
+            r#"[{manager: kube-controller-manager, operation: Update, subresource: scale, fieldsV1: {"f:spec": {"f:replicas": {}}}}]"#,
+        );
+        assert_eq!(
+            None,
+            replicas_until_hpa_handover(Some(&scaled), &autoscaling)
+        );
+    }
+}
@@ -2,10 +2,12 @@
 use std::{
     borrow::Cow,
     collections::{BTreeMap, HashMap},
-    sync::Arc,
+    hash::{Hash, Hasher},
+    sync::{Arc, Mutex},
     time::Duration,
 };
 
+use fnv::FnvHasher;
 use product_config::{
     types::PropertyNameKind,
     writer::{to_java_properties_string, PropertiesWriterError},
@@ -19,7 +21,6 @@ use stackable_operator::{
         pod::{
             container::ContainerBuilder,
             resources::ResourceRequirementsBuilder,
-            security::PodSecurityContextBuilder,
             volume::{
                 SecretOperatorVolumeSourceBuilder, SecretOperatorVolumeSourceBuilderError,
                 VolumeBuilder,
@@ -40,17 +41,30 @@ use stackable_operator::{
         api::{
             apps::v1::{StatefulSet, StatefulSetSpec},
             core::v1::{
-                ConfigMap, ConfigMapVolumeSource, EmptyDirVolumeSource, Probe, SecretVolumeSource,
-                Service, ServicePort, ServiceSpec, TCPSocketAction, Volume,
+                ConfigMap, ConfigMapVolumeSource, EmptyDirVolumeSource, HTTPGetAction, Probe,
+                Secret, SecretVolumeSource, Service, ServicePort, ServiceSpec, Volume,
             },
+            networking::v1::NetworkPolicy,
         },
-        apimachinery::pkg::{apis::meta::v1::LabelSelector, util::intstr::IntOrString},
+        apimachinery::pkg::{
+            api::resource::Quantity, apis::meta::v1::LabelSelector, util::intstr::IntOrString,
+        },
+        DeepMerge,
+    },
+    kube::{
+        runtime::{
+            controller::Action,
+            reflector::{ObjectRef, Store},
+        },
+        Resource, ResourceExt,
     },
-    kube::{runtime::controller::Action, Resource, ResourceExt},
-    kvp::{LabelError, Labels, ObjectLabels},
+    kvp::{Annotation, AnnotationError, Label, LabelError, Labels, ObjectLabels},
     logging::controller::ReconcilerError,
     memory::{BinaryMultiple, MemoryQuantity},
-    product_config_utils::{transform_all_roles_to_config, validate_all_roles_and_groups_config},
+    product_config_utils::{
+        transform_all_roles_to_config, validate_all_roles_and_groups_config,
+        ValidatedRoleConfigByPropertyKind,
+    },
     product_logging::{
         self,
         spec::{
@@ -65,35 +79,113 @@ use stackable_operator::{
     },
 };
 use strum::EnumDiscriminants;
+use tokio_rustls::TlsConnector;
 use tracing::warn;
 
 use crate::{
+    autoscaling::{
+        apply_hpa, build_rolegroup_hpa, delete_orphaned_hpas, replicas_until_hpa_handover,
+    },
     crd::{
-        ConnectorConfig, Container, EDCCluster, EDCClusterStatus, EDCRole, APP_NAME,
-        CONFIG_PROPERTIES, CONTROL_PORT, CONTROL_PORT_NAME, EDC_FS_CONFIG, EDC_IONOS_ACCESS_KEY,
-        EDC_IONOS_ENDPOINT, EDC_IONOS_SECRET_KEY, HTTP_PORT, HTTP_PORT_NAME,
-        JVM_SECURITY_PROPERTIES, LOGGING_PROPERTIES, MANAGEMENT_PORT, MANAGEMENT_PORT_NAME,
-        PROTOCOL_PORT, PROTOCOL_PORT_NAME, PUBLIC_PORT, PUBLIC_PORT_NAME, SECRET_KEY_S3_ACCESS_KEY,
+        ConnectorConfig, Container, EDCCluster, EDCClusterStatus, EDCRole, ProbeTimings, APP_NAME,
+        CONFIG_PROPERTIES, CONTROL_WEB_CONTEXT, DEFAULT_WEB_CONTEXT,
+        EDC_DATAPLANE_TOKEN_VALIDATION_ENDPOINT, EDC_DSP_CALLBACK_ADDRESS, EDC_FS_CONFIG,
+        EDC_IONOS_ACCESS_KEY, EDC_IONOS_ENDPOINT, EDC_IONOS_SECRET_KEY, EDC_PARTICIPANT_ID,
+        JMX_EXPORTER_CONFIG, JVM_SECURITY_PROPERTIES, LOGGING_PROPERTIES, METRICS_PORT,
+        METRICS_PORT_NAME, PROTOCOL_WEB_CONTEXT, SECRET_KEY_S3_ACCESS_KEY,
         SECRET_KEY_S3_SECRET_KEY, STACKABLE_CERTS_DIR, STACKABLE_CERT_MOUNT_DIR,
         STACKABLE_CERT_MOUNT_DIR_NAME, STACKABLE_CONFIG_DIR, STACKABLE_CONFIG_DIR_NAME,
-        STACKABLE_LOG_CONFIG_MOUNT_DIR, STACKABLE_LOG_CONFIG_MOUNT_DIR_NAME, STACKABLE_LOG_DIR,
-        STACKABLE_LOG_DIR_NAME, STACKABLE_SECRETS_DIR,
+        STACKABLE_JMX_DIR, STACKABLE_LOG_CONFIG_MOUNT_DIR, STACKABLE_LOG_CONFIG_MOUNT_DIR_NAME,
+        STACKABLE_LOG_DIR, STACKABLE_LOG_DIR_NAME, STACKABLE_SECRETS_DIR, WEB_CONTEXTS,
     },
+    discovery::build_discovery_configmap,
+    endpoints::{build_endpoint_status, https_enabled},
+    events::{EventPublisher, REPORTED_ROLLOUT_ANNOTATION},
+    graceful_shutdown::{add_graceful_shutdown_config, StoppedConditionBuilder},
+    health::{check_health, HEALTH_CHECK_INTERVAL},
+    network_policy::{build_network_policy, network_policy_name},
+    pdb::build_pdb,
     product_logging::{extend_role_group_config_map, resolve_vector_aggregator_address},
-    OPERATOR_NAME,
+    security_context::{add_security_context, add_vector_security_context},
+    validation, OPERATOR_NAME,
 };
 
 pub const EDC_CONTROLLER_NAME: &str = "edccluster";
-const DOCKER_IMAGE_BASE_NAME: &str = "edc";
+pub const DOCKER_IMAGE_BASE_NAME: &str = "edc";
+
+/// Pod template annotation containing a hash of the rendered configuration, see
+/// [`compute_config_hash`]
+pub const CONFIG_HASH_ANNOTATION: &str = "edc.stackable.tech/config-hash";
+
+/// Memory of the connector container which is not available to the JVM heap, e.g. for the
+/// metaspace, thread stacks and direct buffers
+const JVM_NON_HEAP_MEMORY: MemoryQuantity = MemoryQuantity {
+    value: 512.0,
+    unit: BinaryMultiple::Mebi,
+};
 
 pub const MAX_LOG_FILES_SIZE: MemoryQuantity = MemoryQuantity {
     value: 10.0,
     unit: BinaryMultiple::Mebi,
 };
 
+/// Configuration of the JMX exporter javaagent
+///
+/// The EDC publishes its Micrometer metrics as MBeans in the `metrics` domain, which are named
+/// after the metric and its tags, e.g. `httpServerRequests.exception.None.method.POST.[...]`. The
+/// rules turn them into labeled Prometheus metrics. The JVM metrics are exported by the javaagent
+/// itself.
+const JMX_EXPORTER_CONFIG_YAML: &str = r#"---
+lowercaseOutputName: true
+lowercaseOutputLabelNames: true
+rules:
+  # Requests to the connector APIs, e.g. to negotiate contracts (`/negotiations`,
+  # `/v2/contractnegotiations`) and to transfer data (`/transfers`, `/v2/transferprocesses`)
+  - pattern: 'metrics<name=httpServerRequests\.exception\.([^.]*)\.method\.([^.]*)\.outcome\.([^.]*)\.status\.([^.]*)\.uri\.(.*), type=timers><>Count'
+    name: edc_http_server_requests_total
+    type: COUNTER
+    labels:
+      exception: "$1"
+      method: "$2"
+      outcome: "$3"
+      status: "$4"
+      uri: "$5"
+  - pattern: 'metrics<name=httpServerRequests\.exception\.([^.]*)\.method\.([^.]*)\.outcome\.([^.]*)\.status\.([^.]*)\.uri\.(.*), type=timers><>Mean'
+    name: edc_http_server_requests_duration_mean_milliseconds
+    type: GAUGE
+    labels:
+      exception: "$1"
+      method: "$2"
+      outcome: "$3"
+      status: "$4"
+      uri: "$5"
+  # Executors of the EDC, e.g. of the state machines which process the contract negotiations and
+  # transfer processes
+  - pattern: 'metrics<name=executor(Completed|Active|Queued|PoolSize)\.name\.(.*), type=gauges><>Value'
+    name: edc_executor_$1
+    type: GAUGE
+    labels:
+      name: "$2"
+"#;
+
+/// Delay before the first retry of a failed reconciliation, it is doubled with every further
+/// failure
+const ERROR_BACKOFF_BASE: Duration = Duration::from_secs(5);
+/// Maximum delay before the retry of a failed reconciliation
+const ERROR_BACKOFF_CAP: Duration = Duration::from_secs(300);
+
 pub struct Ctx {
     pub client: stackable_operator::client::Client,
     pub product_config: ProductConfigManager,
+    pub event_publisher: EventPublisher,
+    /// Cache of the StatefulSets, which is filled by the watch of the controller
+    pub statefulsets: Store<StatefulSet>,
+    /// Namespace of the operator, which is allowed to call the connectors in the NetworkPolicy
+    pub operator_namespace: Option<String>,
+    /// Number of consecutive failed reconciliations per EDCCluster
+    pub reconcile_failures: Mutex<HashMap<ObjectRef<EDCCluster>, u32>>,
+    /// TLS client of the health checks
+    pub tls_connector: TlsConnector,
 }
 
 #[derive(Snafu, Debug, EnumDiscriminants)]
@@ -104,6 +196,8 @@ pub enum Error {
     ObjectHasNoNamespace,
     #[snafu(display("object defines no metastore role"))]
     NoServerRole,
+    #[snafu(display("the EDCCluster is invalid at [{}]", source.field()))]
+    InvalidCluster { source: crate::validation::Error },
     #[snafu(display("failed to calculate global service name"))]
     GlobalServiceNameNotFound,
     #[snafu(display("failed to calculate service name for role {rolegroup}"))]
@@ -144,9 +238,11 @@ pub enum Error {
     ObjectMissingMetadataForOwnerRef {
         source: stackable_operator::builder::meta::Error,
     },
+    #[snafu(display("failed to build discovery ConfigMap"))]
+    BuildDiscoveryConfig { source: crate::discovery::Error },
     #[snafu(display("failed to apply discovery ConfigMap"))]
     ApplyDiscoveryConfig {
-        source: stackable_operator::client::Error,
+        source: stackable_operator::cluster_resources::Error,
     },
     #[snafu(display("failed to update status"))]
     ApplyStatus {
@@ -215,6 +311,48 @@ pub enum Error {
     #[snafu(display("failed to build label"))]
     BuildLabel { source: LabelError },
 
+    #[snafu(display("failed to build annotation"))]
+    BuildAnnotation { source: AnnotationError },
+
+    #[snafu(display("the memory limit of the connector is not set"))]
+    MissingMemoryLimit,
+
+    #[snafu(display("failed to parse memory quantity [{quantity:?}]"))]
+    ParseMemoryQuantity {
+        source: stackable_operator::memory::Error,
+        quantity: Quantity,
+    },
+
+    #[snafu(display(
+        "the memory limit {memory_limit:?} leaves no room for the JVM heap besides the non-heap memory"
+    ))]
+    InsufficientMemoryForHeap { memory_limit: Quantity },
+
+    #[snafu(display(
+        "the maximum heap {max:?} does not fit into the memory limit {memory_limit:?} besides the non-heap memory of the JVM"
+    ))]
+    HeapExceedsMemoryLimit {
+        max: Quantity,
+        memory_limit: Quantity,
+    },
+
+    #[snafu(display(
+        "the initial heap {min:?} must not exceed the maximum heap of {max_mebibytes}Mi"
+    ))]
+    HeapMinAboveMax { min: Quantity, max_mebibytes: u32 },
+
+    #[snafu(display("failed to build the endpoints of the status"))]
+    BuildEndpointStatus { source: crate::endpoints::Error },
+
+    #[snafu(display("failed to check the health of the connectors"))]
+    CheckHealth { source: crate::health::Error },
+
+    #[snafu(display("failed to retrieve Secret [{secret}]"))]
+    RetrieveSecret {
+        source: stackable_operator::client::Error,
+        secret: String,
+    },
+
     #[snafu(display("failed to build object meta data"))]
     ObjectMeta {
         source: stackable_operator::builder::meta::Error,
@@ -231,6 +369,49 @@ pub enum Error {
         source: SecretClassVolumeError,
         volume_name: String,
     },
+
+    #[snafu(display("failed to configure the graceful shutdown"))]
+    GracefulShutdown {
+        source: crate::graceful_shutdown::Error,
+    },
+
+    #[snafu(display("failed to build the PodDisruptionBudget"))]
+    BuildPdb { source: crate::pdb::Error },
+
+    #[snafu(display("failed to apply the PodDisruptionBudget"))]
+    ApplyPdb {
+        source: stackable_operator::cluster_resources::Error,
+    },
+
+    #[snafu(display("failed to build the NetworkPolicy"))]
+    BuildNetworkPolicy {
+        source: crate::network_policy::Error,
+    },
+
+    #[snafu(display("failed to apply the NetworkPolicy"))]
+    ApplyNetworkPolicy {
+        source: stackable_operator::client::Error,
+    },
+
+    #[snafu(display("failed to delete the disabled NetworkPolicy"))]
+    DeleteNetworkPolicy {
+        source: stackable_operator::client::Error,
+    },
+
+    #[snafu(display("failed to build the HorizontalPodAutoscaler for {rolegroup}"))]
+    BuildHpa {
+        source: crate::autoscaling::Error,
+        rolegroup: RoleGroupRef<EDCCluster>,
+    },
+
+    #[snafu(display("failed to apply the HorizontalPodAutoscaler for {rolegroup}"))]
+    ApplyHpa {
+        source: crate::autoscaling::Error,
+        rolegroup: RoleGroupRef<EDCCluster>,
+    },
+
+    #[snafu(display("failed to delete the orphaned HorizontalPodAutoscalers"))]
+    DeleteOrphanedHpas { source: crate::autoscaling::Error },
 }
 type Result<T, E = Error> = std::result::Result<T, E>;
 
@@ -240,9 +421,40 @@ impl ReconcilerError for Error {
     }
 }
 
+impl Error {
+    /// Whether the error is caused by an invalid EDCCluster, which cannot be resolved by retrying
+    fn is_configuration_error(&self) -> bool {
+        matches!(
+            self,
+            Error::NoServerRole
+                | Error::InvalidCluster { .. }
+                | Error::GenerateProductConfig { .. }
+                | Error::InvalidProductConfig { .. }
+                | Error::InvalidDbType { .. }
+                | Error::FailedToResolveResourceConfig { .. }
+                | Error::S3TlsNoVerificationNotSupported
+                | Error::JvmSecurityProperties { .. }
+                | Error::MissingMemoryLimit
+                | Error::ParseMemoryQuantity { .. }
+                | Error::InsufficientMemoryForHeap { .. }
+                | Error::HeapExceedsMemoryLimit { .. }
+                | Error::HeapMinAboveMax { .. }
+                | Error::GracefulShutdown { .. }
+        )
+    }
+}
+
 pub async fn reconcile_edc(edc: Arc<EDCCluster>, ctx: Arc<Ctx>) -> Result<Action> {
     tracing::info!("Starting reconcile");
     let client = &ctx.client;
+
+    if let Some(error) = validation::validate(&edc).into_iter().next() {
+        return Err(error).context(InvalidClusterSnafu);
+    }
+    for warning in validation::warnings(&edc) {
+        tracing::warn!(warning, "The EDCCluster is probably misconfigured");
+    }
+
     let resolved_product_image: ResolvedProductImage = edc
         .spec
         .image
@@ -257,30 +469,11 @@ pub async fn reconcile_edc(edc: Arc<EDCCluster>, ctx: Arc<Ctx>) -> Result<Action
         .await
         .context(ResolveS3ConnectionSnafu)?;
 
-    let validated_config = validate_all_roles_and_groups_config(
+    let validated_config = validated_product_config(
+        &edc,
         &resolved_product_image.product_version,
-        &transform_all_roles_to_config(
-            edc.as_ref(),
-            [(
-                EDCRole::Connector.to_string(),
-                (
-                    vec![
-                        PropertyNameKind::Env,
-                        PropertyNameKind::Cli,
-                        PropertyNameKind::File(CONFIG_PROPERTIES.to_string()),
-                        PropertyNameKind::File(JVM_SECURITY_PROPERTIES.to_string()),
-                    ],
-                    edc.spec.connectors.clone().context(NoServerRoleSnafu)?,
-                ),
-            )]
-            .into(),
-        )
-        .context(GenerateProductConfigSnafu)?,
         &ctx.product_config,
-        false,
-        false,
-    )
-    .context(InvalidProductConfigSnafu)?;
+    )?;
 
     let server_config = validated_config
         .get(&EDCRole::Connector.to_string())
@@ -317,7 +510,7 @@ pub async fn reconcile_edc(edc: Arc<EDCCluster>, ctx: Arc<Ctx>) -> Result<Action
     let server_role_service = build_server_role_service(&edc, &resolved_product_image)?;
 
     // we have to get the assigned ports
-    cluster_resources
+    let server_role_service = cluster_resources
         .add(client, server_role_service)
         .await
         .context(ApplyRoleServiceSnafu)?;
@@ -326,7 +519,14 @@ pub async fn reconcile_edc(edc: Arc<EDCCluster>, ctx: Arc<Ctx>) -> Result<Action
         .await
         .context(ResolveVectorAggregatorAddressSnafu)?;
 
+    let referenced_secrets = resolve_referenced_secrets(&edc, client).await?;
+
+    let namespace = edc.namespace().context(ObjectHasNoNamespaceSnafu)?;
+    let edc_ref = ObjectRef::from_obj(&*edc);
     let mut ss_cond_builder = StatefulSetConditionBuilder::default();
+    let mut stopped_cond_builder = StoppedConditionBuilder::new(edc.spec.cluster_operation.stopped);
+    let mut hpas = Vec::new();
+    let mut applied_statefulsets = Vec::new();
 
     for (rolegroup_name, rolegroup_config) in server_config.iter() {
         let rolegroup = edc.server_rolegroup_ref(rolegroup_name);
@@ -345,7 +545,8 @@ pub async fn reconcile_edc(edc: Arc<EDCCluster>, ctx: Arc<Ctx>) -> Result<Action
             s3_bucket_spec.connection.as_ref(),
             vector_aggregator_address.as_deref(),
         )?;
-        let rg_statefulset = build_server_rolegroup_statefulset(
+        let config_hash = compute_config_hash(&rg_configmap, &referenced_secrets);
+        let mut rg_statefulset = build_server_rolegroup_statefulset(
             &edc,
             &resolved_product_image,
             &rolegroup,
@@ -353,6 +554,7 @@ pub async fn reconcile_edc(edc: Arc<EDCCluster>, ctx: Arc<Ctx>) -> Result<Action
             &config,
             s3_bucket_spec.connection.as_ref(),
             &rbac_sa.name_any(),
+            &config_hash,
         )?;
 
         cluster_resources
@@ -369,26 +571,162 @@ pub async fn reconcile_edc(edc: Arc<EDCCluster>, ctx: Arc<Ctx>) -> Result<Action
                 rolegroup: rolegroup.clone(),
             })?;
 
-        ss_cond_builder.add(
-            cluster_resources
-                .add(client, rg_statefulset)
-                .await
-                .context(ApplyRoleGroupStatefulSetSnafu {
+        // The previous state is taken from the cache of the watched StatefulSets
+        let previous_statefulset = ctx
+            .statefulsets
+            .get(&ObjectRef::new(&rolegroup.object_name()).within(&namespace));
+        let previous_config_hash = previous_statefulset
+            .as_deref()
+            .and_then(pod_template_config_hash);
+        let reported_rollout = match &previous_statefulset {
+            Some(previous_statefulset) => {
+                ctx.event_publisher
+                    .report_rollout(&edc_ref, previous_statefulset)
+                    .await
+            }
+            None => None,
+        };
+        if let Some(reported_rollout) = reported_rollout {
+            rg_statefulset
+                .annotations_mut()
+                .insert(REPORTED_ROLLOUT_ANNOTATION.to_string(), reported_rollout);
+        }
+
+        // A stopped cluster must not be scaled up again
+        if let Some(autoscaling) = config
+            .autoscaling
+            .as_ref()
+            .filter(|_| !edc.spec.cluster_operation.stopped)
+        {
+            let hpa = build_rolegroup_hpa(&edc, &resolved_product_image, &rolegroup, autoscaling)
+                .context(BuildHpaSnafu {
+                rolegroup: rolegroup.clone(),
+            })?;
+            if !edc.spec.cluster_operation.reconciliation_paused {
+                apply_hpa(client, &hpa).await.context(ApplyHpaSnafu {
                     rolegroup: rolegroup.clone(),
-                })?,
-        );
+                })?;
+            }
+            hpas.push(hpa);
+
+            if let Some(spec) = rg_statefulset.spec.as_mut() {
+                spec.replicas =
+                    replicas_until_hpa_handover(previous_statefulset.as_deref(), autoscaling);
+            }
+        }
+
+        let rg_statefulset = cluster_resources
+            .add(client, rg_statefulset)
+            .await
+            .context(ApplyRoleGroupStatefulSetSnafu {
+                rolegroup: rolegroup.clone(),
+            })?;
+        ctx.event_publisher
+            .report_config_change(
+                &edc_ref,
+                &rg_statefulset,
+                previous_config_hash.as_deref(),
+                &config_hash,
+            )
+            .await;
+        stopped_cond_builder.add(&rg_statefulset);
+        applied_statefulsets.push(rg_statefulset.clone());
+        ss_cond_builder.add(rg_statefulset);
+    }
+
+    // A disabled PodDisruptionBudget is not added and therefore deleted as orphaned resource
+    if let Some(pdb) = build_pdb(&edc, &EDCRole::Connector).context(BuildPdbSnafu)? {
+        cluster_resources
+            .add(client, pdb)
+            .await
+            .context(ApplyPdbSnafu)?;
+    }
+
+    // NetworkPolicies and HorizontalPodAutoscalers are not managed by the ClusterResources, so
+    // they are deleted explicitly, unless the reconciliation is paused
+    if !edc.spec.cluster_operation.reconciliation_paused {
+        delete_orphaned_hpas(client, &edc, &namespace, &hpas)
+            .await
+            .context(DeleteOrphanedHpasSnafu)?;
+
+        let network_policy = build_network_policy(
+            &edc,
+            &resolved_product_image,
+            &server_config,
+            s3_bucket_spec.connection.as_ref(),
+            vector_aggregator_address.as_deref(),
+            ctx.operator_namespace.as_deref(),
+        )
+        .context(BuildNetworkPolicySnafu)?;
+        if let Some(network_policy) = network_policy {
+            client
+                .apply_patch(EDC_CONTROLLER_NAME, &network_policy, &network_policy)
+                .await
+                .context(ApplyNetworkPolicySnafu)?;
+        } else if let Some(network_policy) = client
+            .get_opt::<NetworkPolicy>(&network_policy_name(&edc, &EDCRole::Connector), &namespace)
+            .await
+            .context(DeleteNetworkPolicySnafu)?
+        {
+            client
+                .delete(&network_policy)
+                .await
+                .context(DeleteNetworkPolicySnafu)?;
+        }
     }
 
     let cluster_operation_cond_builder =
         ClusterOperationsConditionBuilder::new(&edc.spec.cluster_operation);
+    let health_cond_builder = check_health(
+        &ctx.tls_connector,
+        &edc,
+        &server_config,
+        &applied_statefulsets,
+    )
+    .await
+    .context(CheckHealthSnafu)?;
+
+    // The participant and the web contexts are usually configured for the whole role, so the
+    // config of the first rolegroup is published in the status
+    let status_config = server_config
+        .iter()
+        .min_by_key(|(rolegroup_name, _)| *rolegroup_name)
+        .and_then(|(_, rolegroup_config)| {
+            rolegroup_config.get(&PropertyNameKind::File(CONFIG_PROPERTIES.to_string()))
+        });
 
     let status = EDCClusterStatus {
         conditions: compute_conditions(
             edc.as_ref(),
-            &[&ss_cond_builder, &cluster_operation_cond_builder],
+            &[
+                &ss_cond_builder,
+                &health_cond_builder,
+                &cluster_operation_cond_builder,
+                &stopped_cond_builder,
+            ],
         ),
+        observed_generation: edc.metadata.generation,
+        participant_id: status_config.and_then(|config| config.get(EDC_PARTICIPANT_ID).cloned()),
+        dsp_callback_address: status_config
+            .and_then(|config| config.get(EDC_DSP_CALLBACK_ADDRESS).cloned()),
+        endpoints: build_endpoint_status(
+            client,
+            &edc,
+            &server_role_service,
+            &applied_statefulsets,
+            status_config,
+        )
+        .await
+        .context(BuildEndpointStatusSnafu)?,
     };
 
+    let discovery_cm = build_discovery_configmap(&edc, &resolved_product_image, &status)
+        .context(BuildDiscoveryConfigSnafu)?;
+    cluster_resources
+        .add(client, discovery_cm)
+        .await
+        .context(ApplyDiscoveryConfigSnafu)?;
+
     client
         .apply_patch_status(OPERATOR_NAME, &*edc, &status)
         .await
@@ -399,7 +737,73 @@ pub async fn reconcile_edc(edc: Arc<EDCCluster>, ctx: Arc<Ctx>) -> Result<Action
         .await
         .context(DeleteOrphanedResourcesSnafu)?;
 
-    Ok(Action::await_change())
+    ctx.reconcile_failures
+        .lock()
+        .expect("lock is not poisoned")
+        .remove(&edc_ref);
+
+    // The reconciliation is repeated to refresh the health of the connectors
+    Ok(Action::requeue(HEALTH_CHECK_INTERVAL))
+}
+
+/// Merge the role and rolegroup configs of the EDCCluster with the operator defaults and validate
+/// them against the product config
+pub fn validated_product_config(
+    edc: &EDCCluster,
+    product_version: &str,
+    product_config: &ProductConfigManager,
+) -> Result<ValidatedRoleConfigByPropertyKind> {
+    let mut validated_config = validate_all_roles_and_groups_config(
+        product_version,
+        &transform_all_roles_to_config(
+            edc,
+            [(
+                EDCRole::Connector.to_string(),
+                (
+                    vec![
+                        PropertyNameKind::Env,
+                        PropertyNameKind::Cli,
+                        PropertyNameKind::File(CONFIG_PROPERTIES.to_string()),
+                        PropertyNameKind::File(JVM_SECURITY_PROPERTIES.to_string()),
+                    ],
+                    edc.spec.connectors.clone().context(NoServerRoleSnafu)?,
+                ),
+            )]
+            .into(),
+        )
+        .context(GenerateProductConfigSnafu)?,
+        product_config,
+        false,
+        false,
+    )
+    .context(InvalidProductConfigSnafu)?;
+
+    // The addresses of the protocol and the control API are derived from the merged paths, which
+    // can be changed with configOverrides, unless the addresses are overridden themselves
+    let name = edc.name_unchecked();
+    for rolegroup_config in validated_config.values_mut().flat_map(HashMap::values_mut) {
+        let config_properties = rolegroup_config
+            .entry(PropertyNameKind::File(CONFIG_PROPERTIES.to_string()))
+            .or_default();
+        let dsp_callback_address = format!(
+            "http://{name}:{port}{path}",
+            port = PROTOCOL_WEB_CONTEXT.port,
+            path = PROTOCOL_WEB_CONTEXT.path(Some(config_properties))
+        );
+        let token_validation_endpoint = format!(
+            "http://{name}:{port}{path}/token",
+            port = CONTROL_WEB_CONTEXT.port,
+            path = CONTROL_WEB_CONTEXT.path(Some(config_properties))
+        );
+        config_properties
+            .entry(EDC_DSP_CALLBACK_ADDRESS.to_string())
+            .or_insert(dsp_callback_address);
+        config_properties
+            .entry(EDC_DATAPLANE_TOKEN_VALIDATION_ENDPOINT.to_string())
+            .or_insert(token_validation_endpoint);
+    }
+
+    Ok(validated_config)
 }
 
 pub fn build_server_role_service(
@@ -431,7 +835,8 @@ pub fn build_server_role_service(
 
     let service_spec = ServiceSpec {
         type_: Some(edc.spec.cluster_config.listener_class.k8s_service_type()),
-        ports: Some(service_ports()),
+        // Metrics are scraped from the rolegroup Services and not exposed to clients
+        ports: Some(service_ports(false)),
         selector: Some(service_selector_labels.into()),
         ..ServiceSpec::default()
     };
@@ -444,7 +849,7 @@ pub fn build_server_role_service(
 }
 
 /// The rolegroup [`ConfigMap`] configures the rolegroup based on the configuration given by the administrator
-fn build_connector_rolegroup_config_map(
+pub fn build_connector_rolegroup_config_map(
     edc: &EDCCluster,
     resolved_product_image: &ResolvedProductImage,
     rolegroup: &RoleGroupRef<EDCCluster>,
@@ -453,30 +858,23 @@ fn build_connector_rolegroup_config_map(
     s3_conn: Option<&S3ConnectionSpec>,
     vector_aggregator_address: Option<&str>,
 ) -> Result<ConfigMap> {
-    let mut config_properties = String::new();
-
-    for (property_name_kind, config) in role_group_config {
-        let mut conf: BTreeMap<String, Option<String>> = Default::default();
-        match property_name_kind {
-            PropertyNameKind::File(file_name) if file_name == CONFIG_PROPERTIES => {
-                if let Some(conn) = s3_conn {
-                    if let Some(endpoint) = conn.endpoint() {
-                        conf.insert(EDC_IONOS_ENDPOINT.to_string(), Some(endpoint));
-                    }
-                }
-
-                let transformed_config: BTreeMap<String, Option<String>> = config
-                    .iter()
-                    .map(|(k, v)| (k.clone(), Some(v.clone())))
-                    .collect();
-                conf.extend(transformed_config);
-
-                config_properties =
-                    to_java_properties_string(conf.iter()).context(PropertiesWriteSnafu)?;
-            }
-            _ => {}
-        }
+    // The validated config already contains the operator defaults, the settings derived from the
+    // EDCCluster and the role and rolegroup configOverrides (in this order of precedence), see the
+    // `Configuration` implementation of `ConnectorConfigFragment`.
+    // The S3 endpoint is derived from the resolved S3 connection, which the product config does not
+    // know about, so it is inserted first and can still be changed with configOverrides.
+    let mut config_properties: BTreeMap<String, Option<String>> = BTreeMap::new();
+    if let Some(endpoint) = s3_conn.and_then(|conn| conn.endpoint()) {
+        config_properties.insert(EDC_IONOS_ENDPOINT.to_string(), Some(endpoint));
     }
+    config_properties.extend(
+        role_group_config
+            .get(&PropertyNameKind::File(CONFIG_PROPERTIES.to_string()))
+            .cloned()
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(k, v)| (k, Some(v))),
+    );
 
     // build JVM security properties from configOverrides.
     let jvm_sec_props: BTreeMap<String, Option<String>> = role_group_config
@@ -505,7 +903,10 @@ fn build_connector_rolegroup_config_map(
 
     cm_builder
         .metadata(cm_metadata)
-        .add_data(CONFIG_PROPERTIES, config_properties)
+        .add_data(
+            CONFIG_PROPERTIES,
+            to_java_properties_string(config_properties.iter()).context(PropertiesWriteSnafu)?,
+        )
         .add_data(
             JVM_SECURITY_PROPERTIES,
             to_java_properties_string(jvm_sec_props.iter()).with_context(|_| {
@@ -515,6 +916,10 @@ fn build_connector_rolegroup_config_map(
             })?,
         );
 
+    if edc.metrics_enabled() {
+        cm_builder.add_data(JMX_EXPORTER_CONFIG, JMX_EXPORTER_CONFIG_YAML);
+    }
+
     extend_role_group_config_map(
         rolegroup,
         vector_aggregator_address,
@@ -535,12 +940,13 @@ fn build_connector_rolegroup_config_map(
 /// The rolegroup [`Service`] is a headless service that allows direct access to the instances of a certain rolegroup
 ///
 /// This is mostly useful for internal communication between peers, or for clients that perform client-side load balancing.
-fn build_rolegroup_service(
+pub fn build_rolegroup_service(
     edc: &EDCCluster,
     resolved_product_image: &ResolvedProductImage,
     rolegroup: &RoleGroupRef<EDCCluster>,
 ) -> Result<Service> {
-    let metadata = ObjectMetaBuilder::new()
+    let mut metadata_builder = ObjectMetaBuilder::new();
+    metadata_builder
         .name_and_namespace(edc)
         .name(rolegroup.object_name())
         .ownerreference_from_resource(edc, None, Some(true))
@@ -551,8 +957,28 @@ fn build_rolegroup_service(
             &rolegroup.role,
             &rolegroup.role_group,
         ))
-        .context(ObjectMetaSnafu)?
-        .build();
+        .context(ObjectMetaSnafu)?;
+
+    if edc.metrics_enabled() {
+        // The label allows a ServiceMonitor to select the Services, the annotations are used by
+        // the Kubernetes service discovery of Prometheus
+        metadata_builder
+            .with_label(Label::try_from(("prometheus.io/scrape", "true")).context(BuildLabelSnafu)?)
+            .with_annotation(
+                Annotation::try_from(("prometheus.io/scrape", "true"))
+                    .context(BuildAnnotationSnafu)?,
+            )
+            .with_annotation(
+                Annotation::try_from(("prometheus.io/port", METRICS_PORT.to_string().as_str()))
+                    .context(BuildAnnotationSnafu)?,
+            )
+            .with_annotation(
+                Annotation::try_from(("prometheus.io/path", "/metrics"))
+                    .context(BuildAnnotationSnafu)?,
+            );
+    }
+
+    let metadata = metadata_builder.build();
 
     let service_selector_labels =
         Labels::role_group_selector(edc, APP_NAME, &rolegroup.role, &rolegroup.role_group)
@@ -562,7 +988,7 @@ fn build_rolegroup_service(
         // Internal communication does not need to be exposed
         type_: Some("ClusterIP".to_string()),
         cluster_ip: Some("None".to_string()),
-        ports: Some(service_ports()),
+        ports: Some(service_ports(edc.metrics_enabled())),
         selector: Some(service_selector_labels.into()),
         publish_not_ready_addresses: Some(true),
         ..ServiceSpec::default()
@@ -579,7 +1005,11 @@ fn build_rolegroup_service(
 ///
 /// The [`Pod`](`stackable_operator::k8s_openapi::api::core::v1::Pod`)s are accessible through the
 /// corresponding [`Service`] (from [`build_rolegroup_service`]).
-fn build_server_rolegroup_statefulset(
+///
+/// The `config_hash` is added as an annotation to the Pod template, so that a change of the
+/// configuration rolls out the Pods.
+#[allow(clippy::too_many_arguments)]
+pub fn build_server_rolegroup_statefulset(
     edc: &EDCCluster,
     resolved_product_image: &ResolvedProductImage,
     rolegroup_ref: &RoleGroupRef<EDCCluster>,
@@ -587,14 +1017,14 @@ fn build_server_rolegroup_statefulset(
     merged_config: &ConnectorConfig,
     s3_conn: Option<&S3ConnectionSpec>,
     sa_name: &str,
+    config_hash: &str,
 ) -> Result<StatefulSet> {
-    let rolegroup = edc
-        .spec
-        .connectors
-        .as_ref()
-        .context(NoServerRoleSnafu)?
-        .role_groups
-        .get(&rolegroup_ref.role_group);
+    let role = edc
+        .get_role(&EDCRole::Connector)
+        .context(NoServerRoleSnafu)?;
+    let rolegroup = role.role_groups.get(&rolegroup_ref.role_group);
+    let connector_config =
+        metastore_config.get(&PropertyNameKind::File(CONFIG_PROPERTIES.to_string()));
     let mut container_builder =
         ContainerBuilder::new(APP_NAME).context(FailedToCreateEdcContainerSnafu {
             name: APP_NAME.to_string(),
@@ -630,14 +1060,12 @@ fn build_server_rolegroup_statefulset(
     // S3
     add_s3_volume_and_volume_mounts(s3_conn, &mut container_builder, &mut pod_builder)?;
 
-    let mut java_cmd = vec![];
-    java_cmd.extend(args);
-    java_cmd.push("java".to_string());
-    java_cmd.push(format!(
+    let mut jvm_args = construct_heap_jvm_args(merged_config)?;
+    jvm_args.push(format!(
         "-D{}={}/{}",
         EDC_FS_CONFIG, STACKABLE_CONFIG_DIR, CONFIG_PROPERTIES
     ));
-    java_cmd.push(format!(
+    jvm_args.push(format!(
         "-Djava.util.logging.config.file={}/{}",
         STACKABLE_CONFIG_DIR, LOGGING_PROPERTIES
     ));
@@ -646,17 +1074,47 @@ fn build_server_rolegroup_statefulset(
     if let Some(c) = s3_conn {
         if c.credentials.is_some() {
             let path = format!("{}/{}", STACKABLE_SECRETS_DIR, SECRET_KEY_S3_ACCESS_KEY);
-            java_cmd.push(format!("-D{}=$(cat {})", EDC_IONOS_ACCESS_KEY, path));
+            jvm_args.push(format!("-D{}=$(cat {})", EDC_IONOS_ACCESS_KEY, path));
             let path = format!("{}/{}", STACKABLE_SECRETS_DIR, SECRET_KEY_S3_SECRET_KEY);
-            java_cmd.push(format!("-D{}=$(cat {})", EDC_IONOS_SECRET_KEY, path));
+            jvm_args.push(format!("-D{}=$(cat {})", EDC_IONOS_SECRET_KEY, path));
         }
     }
 
     // JVM security properties configured via configOverrides
-    java_cmd.push(format!(
+    jvm_args.push(format!(
         "-Djava.security.properties={STACKABLE_CONFIG_DIR}/{JVM_SECURITY_PROPERTIES}"
     ));
 
+    for context in WEB_CONTEXTS {
+        container_builder.add_container_port(context.port_name, context.port.into());
+    }
+
+    if edc.metrics_enabled() {
+        jvm_args.push(format!(
+            "-javaagent:{STACKABLE_JMX_DIR}/jmx_prometheus_javaagent.jar={METRICS_PORT}:{STACKABLE_CONFIG_DIR}/{JMX_EXPORTER_CONFIG}"
+        ));
+        container_builder.add_container_port(METRICS_PORT_NAME, METRICS_PORT.into());
+    }
+
+    if let Some(jvm_argument_overrides) = &merged_config.jvm_argument_overrides {
+        jvm_args = jvm_argument_overrides.apply(jvm_args);
+    }
+
+    add_graceful_shutdown_config(
+        merged_config,
+        connector_config,
+        &mut pod_builder,
+        &mut container_builder,
+    )
+    .context(GracefulShutdownSnafu)?;
+
+    add_security_context(merged_config, &mut pod_builder, &mut container_builder);
+
+    let mut java_cmd = vec![];
+    java_cmd.extend(args);
+    java_cmd.push("java".to_string());
+    java_cmd.extend(jvm_args);
+
     // We add this at the and, as the .jar file should be the last argument to the call to the java binary
     java_cmd.extend(vec!["-jar".to_string(), "connector.jar".to_string()]);
 
@@ -672,31 +1130,22 @@ fn build_server_rolegroup_statefulset(
             STACKABLE_LOG_CONFIG_MOUNT_DIR_NAME,
             STACKABLE_LOG_CONFIG_MOUNT_DIR,
         )
-        .add_container_port(HTTP_PORT_NAME, HTTP_PORT.into())
-        .add_container_port(CONTROL_PORT_NAME, CONTROL_PORT.into())
-        .add_container_port(MANAGEMENT_PORT_NAME, MANAGEMENT_PORT.into())
-        .add_container_port(PROTOCOL_PORT_NAME, PROTOCOL_PORT.into())
-        .add_container_port(PUBLIC_PORT_NAME, PUBLIC_PORT.into())
         .resources(merged_config.resources.clone().into())
-        .readiness_probe(Probe {
-            initial_delay_seconds: Some(10),
-            period_seconds: Some(10),
-            failure_threshold: Some(50),
-            tcp_socket: Some(TCPSocketAction {
-                port: IntOrString::String(HTTP_PORT_NAME.to_string()),
-                ..TCPSocketAction::default()
-            }),
-            ..Probe::default()
-        })
-        .liveness_probe(Probe {
-            initial_delay_seconds: Some(60),
-            period_seconds: Some(20),
-            tcp_socket: Some(TCPSocketAction {
-                port: IntOrString::String(HTTP_PORT_NAME.to_string()),
-                ..TCPSocketAction::default()
-            }),
-            ..Probe::default()
-        })
+        .startup_probe(observability_api_probe(
+            connector_config,
+            "startup",
+            &merged_config.probes.startup,
+        ))
+        .readiness_probe(observability_api_probe(
+            connector_config,
+            "readiness",
+            &merged_config.probes.readiness,
+        ))
+        .liveness_probe(observability_api_probe(
+            connector_config,
+            "liveness",
+            &merged_config.probes.liveness,
+        ))
         .add_env_var_from_secret(
             "EDC_IONOS_TOKEN",
             edc.spec.cluster_config.ionos.token_secret.to_owned(),
@@ -712,6 +1161,10 @@ fn build_server_rolegroup_statefulset(
             &rolegroup_ref.role_group,
         ))
         .context(ObjectMetaSnafu)?
+        .with_annotation(
+            Annotation::try_from((CONFIG_HASH_ANNOTATION, config_hash))
+                .context(BuildAnnotationSnafu)?,
+        )
         .build();
 
     pod_builder
@@ -745,14 +1198,7 @@ fn build_server_rolegroup_statefulset(
             ..Default::default()
         })
         .affinity(&merged_config.affinity)
-        .service_account_name(sa_name)
-        .security_context(
-            PodSecurityContextBuilder::new()
-                .run_as_user(1000)
-                .run_as_group(0)
-                .fs_group(1000)
-                .build(),
-        );
+        .service_account_name(sa_name);
 
     if let Some(ContainerLogConfig {
         choice:
@@ -781,7 +1227,7 @@ fn build_server_rolegroup_statefulset(
     }
 
     if merged_config.logging.enable_vector_agent {
-        pod_builder.add_container(product_logging::framework::vector_container(
+        let mut vector_container = product_logging::framework::vector_container(
             resolved_product_image,
             STACKABLE_CONFIG_DIR_NAME,
             STACKABLE_LOG_DIR_NAME,
@@ -792,7 +1238,9 @@ fn build_server_rolegroup_statefulset(
                 .with_memory_request("128Mi")
                 .with_memory_limit("128Mi")
                 .build(),
-        ));
+        );
+        add_vector_security_context(&mut pod_builder, &mut vector_container);
+        pod_builder.add_container(vector_container);
     }
 
     let metadata = ObjectMetaBuilder::new()
@@ -817,15 +1265,33 @@ fn build_server_rolegroup_statefulset(
     )
     .context(BuildLabelSnafu)?;
 
+    // podOverrides are applied on top of the generated Pod template, the ones of the rolegroup
+    // take precedence over the ones of the role
+    let mut pod_template = pod_builder.build_template();
+    pod_template.merge_from(role.config.pod_overrides.clone());
+    if let Some(rolegroup) = rolegroup {
+        pod_template.merge_from(rolegroup.config.pod_overrides.clone());
+    }
+
     let service_spec = StatefulSetSpec {
         pod_management_policy: Some("Parallel".to_string()),
-        replicas: rolegroup.and_then(|rg| rg.replicas).map(i32::from),
+        // The replicas are also set to 0 by the ClusterResources of a stopped cluster, setting them
+        // here keeps the rendered StatefulSet consistent with the applied one. The replicas of an
+        // autoscaled rolegroup are left to the HorizontalPodAutoscaler, the reconciliation keeps the
+        // current ones until the HorizontalPodAutoscaler took them over.
+        replicas: if edc.spec.cluster_operation.stopped {
+            Some(0)
+        } else if merged_config.autoscaling.is_some() {
+            None
+        } else {
+            rolegroup.and_then(|rg| rg.replicas).map(i32::from)
+        },
         selector: LabelSelector {
             match_labels: Some(service_match_labels.into()),
             ..LabelSelector::default()
         },
         service_name: rolegroup_ref.object_name(),
-        template: pod_builder.build_template(),
+        template: pod_template,
         volume_claim_templates: Some(vec![merged_config
             .resources
             .storage
@@ -841,6 +1307,82 @@ fn build_server_rolegroup_statefulset(
     })
 }
 
+/// Construct the `-Xmx` and `-Xms` arguments of the connector JVM.
+///
+/// The heap is taken from `memory.runtimeLimits` if set, otherwise it is the memory limit minus
+/// [`JVM_NON_HEAP_MEMORY`]. The initial heap defaults to the maximum heap. A heap which does not
+/// fit into the memory limit besides the non-heap memory is rejected, because the container would
+/// be OOMKilled.
+pub fn construct_heap_jvm_args(merged_config: &ConnectorConfig) -> Result<Vec<String>> {
+    let memory = &merged_config.resources.memory;
+    let available_heap = match &memory.limit {
+        Some(memory_limit) => Some((
+            memory_limit,
+            parse_memory_quantity(memory_limit)? - JVM_NON_HEAP_MEMORY,
+        )),
+        None => None,
+    };
+
+    let heap_max = match (&memory.runtime_limits.max, available_heap) {
+        (Some(max), available_heap) => {
+            let heap_max = parse_memory_quantity(max)?;
+            if let Some((memory_limit, available_heap)) = available_heap {
+                if heap_max.scale_to(BinaryMultiple::Mebi).value
+                    > available_heap.scale_to(BinaryMultiple::Mebi).value
+                {
+                    return HeapExceedsMemoryLimitSnafu {
+                        max: max.clone(),
+                        memory_limit: memory_limit.clone(),
+                    }
+                    .fail();
+                }
+            }
+            heap_max
+        }
+        (None, Some((memory_limit, available_heap))) => {
+            if available_heap.value <= 0.0 {
+                return InsufficientMemoryForHeapSnafu {
+                    memory_limit: memory_limit.clone(),
+                }
+                .fail();
+            }
+            available_heap
+        }
+        (None, None) => return MissingMemoryLimitSnafu.fail(),
+    };
+    let heap_min = match &memory.runtime_limits.min {
+        Some(min) => {
+            let heap_min = parse_memory_quantity(min)?;
+            if heap_min.scale_to(BinaryMultiple::Mebi).value
+                > heap_max.scale_to(BinaryMultiple::Mebi).value
+            {
+                return HeapMinAboveMaxSnafu {
+                    min: min.clone(),
+                    max_mebibytes: to_mebibytes(heap_max),
+                }
+                .fail();
+            }
+            heap_min
+        }
+        None => heap_max,
+    };
+
+    Ok(vec![
+        format!("-Xmx{}m", to_mebibytes(heap_max)),
+        format!("-Xms{}m", to_mebibytes(heap_min)),
+    ])
+}
+
+fn parse_memory_quantity(quantity: &Quantity) -> Result<MemoryQuantity> {
+    MemoryQuantity::try_from(quantity).context(ParseMemoryQuantitySnafu {
+        quantity: quantity.clone(),
+    })
+}
+
+fn to_mebibytes(quantity: MemoryQuantity) -> u32 {
+    quantity.scale_to(BinaryMultiple::Mebi).floor().value as u32
+}
+
 fn add_s3_volume_and_volume_mounts(
     s3_conn: Option<&S3ConnectionSpec>,
     cb_druid: &mut ContainerBuilder,
@@ -890,43 +1432,153 @@ fn add_s3_volume_and_volume_mounts(
     Ok(())
 }
 
-pub fn error_policy(_obj: Arc<EDCCluster>, _error: &Error, _ctx: Arc<Ctx>) -> Action {
-    Action::requeue(Duration::from_secs(5))
+/// Retrieve the Secrets which are referenced in the cluster spec and consumed by the connector
+async fn resolve_referenced_secrets(
+    edc: &EDCCluster,
+    client: &stackable_operator::client::Client,
+) -> Result<Vec<Secret>> {
+    let namespace = edc.namespace().context(ObjectHasNoNamespaceSnafu)?;
+    let cluster_config = &edc.spec.cluster_config;
+
+    let mut secrets = Vec::new();
+    for secret_name in [
+        &cluster_config.ionos.token_secret,
+        &cluster_config.cert_secret,
+    ] {
+        secrets.push(
+            client
+                .get::<Secret>(secret_name, &namespace)
+                .await
+                .context(RetrieveSecretSnafu {
+                    secret: secret_name.to_owned(),
+                })?,
+        );
+    }
+
+    Ok(secrets)
 }
 
-fn service_ports() -> Vec<ServicePort> {
-    vec![
-        ServicePort {
-            name: Some(HTTP_PORT_NAME.to_string()),
-            port: HTTP_PORT.into(),
-            protocol: Some("TCP".to_string()),
-            ..ServicePort::default()
-        },
-        ServicePort {
-            name: Some(CONTROL_PORT_NAME.to_string()),
-            port: CONTROL_PORT.into(),
-            protocol: Some("TCP".to_string()),
-            ..ServicePort::default()
-        },
-        ServicePort {
-            name: Some(MANAGEMENT_PORT_NAME.to_string()),
-            port: MANAGEMENT_PORT.into(),
-            protocol: Some("TCP".to_string()),
-            ..ServicePort::default()
-        },
-        ServicePort {
-            name: Some(PROTOCOL_PORT_NAME.to_string()),
-            port: PROTOCOL_PORT.into(),
+/// Hash the rendered rolegroup [`ConfigMap`] together with the resource versions of the referenced
+/// [`Secret`]s.
+///
+/// The connector only reads its configuration on startup, so the hash is used to roll the Pods
+/// whenever any of it changes.
+pub fn compute_config_hash(config_map: &ConfigMap, secrets: &[Secret]) -> String {
+    let mut hasher = FnvHasher::default();
+
+    // The data is stored in a BTreeMap, so the iteration order is stable
+    for (key, value) in config_map.data.iter().flatten() {
+        key.hash(&mut hasher);
+        value.hash(&mut hasher);
+    }
+    for secret in secrets {
+        secret.metadata.name.hash(&mut hasher);
+        secret.metadata.resource_version.hash(&mut hasher);
+    }
+
+    format!("{:016x}", hasher.finish())
+}
+
+pub fn error_policy(obj: Arc<EDCCluster>, error: &Error, ctx: Arc<Ctx>) -> Action {
+    failure_action(&ctx.reconcile_failures, ObjectRef::from_obj(&*obj), error)
+}
+
+/// Count the failed reconciliation of the given EDCCluster and decide when it is retried
+fn failure_action(
+    reconcile_failures: &Mutex<HashMap<ObjectRef<EDCCluster>, u32>>,
+    edc: ObjectRef<EDCCluster>,
+    error: &Error,
+) -> Action {
+    let mut reconcile_failures = reconcile_failures.lock().expect("lock is not poisoned");
+
+    // Retrying does not help with an invalid configuration, the EDCCluster is reconciled again
+    // once it or one of the watched objects changes. The backoff starts again afterwards, because
+    // the change usually fixed the configuration.
+    if error.is_configuration_error() {
+        reconcile_failures.remove(&edc);
+        return Action::await_change();
+    }
+
+    let failures = reconcile_failures.entry(edc).or_default();
+    *failures = failures.saturating_add(1);
+
+    Action::requeue(error_backoff(*failures))
+}
+
+/// Delay before the next retry after the given number of consecutive failures
+fn error_backoff(failures: u32) -> Duration {
+    ERROR_BACKOFF_BASE
+        .saturating_mul(2u32.saturating_pow(failures.saturating_sub(1)))
+        .min(ERROR_BACKOFF_CAP)
+}
+
+fn service_ports(metrics_enabled: bool) -> Vec<ServicePort> {
+    let mut ports = WEB_CONTEXTS
+        .iter()
+        .map(|context| ServicePort {
+            name: Some(context.port_name.to_string()),
+            port: context.port.into(),
             protocol: Some("TCP".to_string()),
             ..ServicePort::default()
-        },
-        ServicePort {
-            name: Some(PUBLIC_PORT_NAME.to_string()),
-            port: PUBLIC_PORT.into(),
+        })
+        .collect::<Vec<_>>();
+
+    if metrics_enabled {
+        ports.push(ServicePort {
+            name: Some(METRICS_PORT_NAME.to_string()),
+            port: METRICS_PORT.into(),
             protocol: Some("TCP".to_string()),
             ..ServicePort::default()
-        },
-    ]
+        });
+    }
+
+    ports
+}
+
+/// HTTP probe against the given check of the EDC observability API, which is served in the default
+/// web context.
+///
+/// HTTPS is used if a keystore for the web server is configured in the connector config.
+fn observability_api_probe(
+    connector_config: Option<&BTreeMap<String, String>>,
+    check: &str,
+    timings: &ProbeTimings,
+) -> Probe {
+    let scheme = if https_enabled(connector_config) {
+        "HTTPS"
+    } else {
+        "HTTP"
+    };
+
+    Probe {
+        http_get: Some(HTTPGetAction {
+            path: Some(format!(
+                "{web_http_path}/check/{check}",
+                web_http_path = DEFAULT_WEB_CONTEXT.path(connector_config)
+            )),
+            port: IntOrString::String(DEFAULT_WEB_CONTEXT.port_name.to_string()),
+            scheme: Some(scheme.to_string()),
+            ..HTTPGetAction::default()
+        }),
+        period_seconds: Some(timings.period_seconds),
+        timeout_seconds: Some(timings.timeout_seconds),
+        failure_threshold: Some(timings.failure_threshold),
+        ..Probe::default()
+    }
+}
+
+/// The config hash of the Pod template of the given StatefulSet, see [`compute_config_hash`]
+fn pod_template_config_hash(statefulset: &StatefulSet) -> Option<String> {
+    statefulset
+        .spec
+        .as_ref()?
+        .template
+        .metadata
+        .as_ref()?
+        .annotations
+        .as_ref()?
+        .get(CONFIG_HASH_ANNOTATION)
+        .cloned()
 }
 
 /// Creates recommended `ObjectLabels` to be used in deployed resources
@@ -946,3 +1598,674 @@ pub fn build_recommended_labels<'a, T>(
         role_group,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fixtures::{edc_cluster, edc_cluster_with_spec};
+
+    fn build_statefulset(edc: &EDCCluster) -> StatefulSet {
+        let resolved_product_image = edc.spec.image.resolve(DOCKER_IMAGE_BASE_NAME, "0.0.0-dev");
+        let rolegroup_ref = edc.server_rolegroup_ref("default");
+        let merged_config = edc
+            .merged_config(&EDCRole::Connector, &rolegroup_ref.role_group)
+            .expect("failed to merge the config");
+
+        build_server_rolegroup_statefulset(
+            edc,
+            &resolved_product_image,
+            &rolegroup_ref,
+            &HashMap::new(),
+            &merged_config,
+            None,
+            "edc-serviceaccount",
+            "0123456789abcdef",
+        )
+        .expect("failed to build the StatefulSet")
+    }
+
+    #[test]
+    fn test_pod_overrides() {
+        let edc = edc_cluster(
+            "
+            podOverrides:
+              spec:
+                securityContext:
+                  runAsUser: 2000
+                nodeSelector:
+                  disktype: ssd
+                  zone: role-zone
+                tolerations:
+                  - key: dedicated
+                    operator: Equal
+                    value: edc
+                    effect: NoSchedule
+            roleGroups:
+              default:
+                replicas: 1
+                podOverrides:
+                  spec:
+                    nodeSelector:
+                      zone: rolegroup-zone
+            ",
+        );
+
+        let pod_spec = build_statefulset(&edc).spec.unwrap().template.spec.unwrap();
+
+        // Role overrides take precedence over the generated Pod template
+        assert_eq!(
+            Some(2000),
+            pod_spec.security_context.and_then(|sc| sc.run_as_user)
+        );
+        assert_eq!(
+            vec!["dedicated"],
+            pod_spec
+                .tolerations
+                .unwrap_or_default()
+                .into_iter()
+                .filter_map(|toleration| toleration.key)
+                .collect::<Vec<_>>()
+        );
+
+        // Rolegroup overrides take precedence over role overrides
+        let node_selector = pod_spec.node_selector.unwrap_or_default();
+        assert_eq!(
+            Some("ssd"),
+            node_selector.get("disktype").map(String::as_str)
+        );
+        assert_eq!(
+            Some("rolegroup-zone"),
+            node_selector.get("zone").map(String::as_str)
+        );
+    }
+
+    #[test]
+    fn test_no_pod_overrides() {
+        let edc = edc_cluster(
+            "
+            roleGroups:
+              default:
+                replicas: 1
+            ",
+        );
+
+        let pod_spec = build_statefulset(&edc).spec.unwrap().template.spec.unwrap();
+
+        assert_eq!(
+            Some(1000),
+            pod_spec.security_context.and_then(|sc| sc.run_as_user)
+        );
+        assert_eq!(None, pod_spec.tolerations);
+        assert_eq!(None, pod_spec.node_selector);
+    }
+
+    #[test]
+    fn test_graceful_shutdown() {
+        let edc = edc_cluster_with_spec(
+            "
+            clusterOperation:
+              stopped: true
+            ",
+            "
+            config:
+              gracefulShutdownTimeout: 10m
+            roleGroups:
+              default:
+                replicas: 3
+            ",
+        );
+
+        let spec = build_statefulset(&edc).spec.unwrap();
+        let pod_spec = spec.template.spec.unwrap();
+        let container = pod_spec
+            .containers
+            .into_iter()
+            .find(|container| container.name == APP_NAME)
+            .unwrap();
+
+        assert_eq!(Some(0), spec.replicas);
+        assert_eq!(Some(600), pod_spec.termination_grace_period_seconds);
+        let pre_stop_command = container
+            .lifecycle
+            .and_then(|lifecycle| lifecycle.pre_stop)
+            .and_then(|pre_stop| pre_stop.exec)
+            .and_then(|exec| exec.command)
+            .unwrap();
+        assert!(pre_stop_command[2].contains("deadline=$((SECONDS + 585))"));
+    }
+
+    #[test]
+    fn test_security_context() {
+        let edc = edc_cluster(
+            "
+            config:
+              logging:
+                enableVectorAgent: true
+              securityContext:
+                runAsUser: 1001
+            roleGroups:
+              default:
+                replicas: 1
+                config:
+                  securityContext:
+                    fsGroup: 2000
+            ",
+        );
+
+        let pod_spec = build_statefulset(&edc).spec.unwrap().template.spec.unwrap();
+
+        let pod_security_context = pod_spec.security_context.unwrap();
+        assert_eq!(Some(1001), pod_security_context.run_as_user);
+        assert_eq!(Some(0), pod_security_context.run_as_group);
+        assert_eq!(Some(2000), pod_security_context.fs_group);
+        assert_eq!(Some(true), pod_security_context.run_as_non_root);
+
+        assert_eq!(2, pod_spec.containers.len());
+        for container in pod_spec.containers {
+            let security_context = container.security_context.unwrap();
+            assert_eq!(Some(true), security_context.read_only_root_filesystem);
+            assert_eq!(Some(false), security_context.allow_privilege_escalation);
+            assert_eq!(
+                Some(vec!["ALL".to_string()]),
+                security_context.capabilities.and_then(|caps| caps.drop)
+            );
+            assert_eq!(
+                Some("RuntimeDefault".to_string()),
+                security_context
+                    .seccomp_profile
+                    .map(|profile| profile.type_)
+            );
+        }
+    }
+
+    #[test]
+    fn test_autoscaled_replicas() {
+        let edc = edc_cluster(
+            "
+            roleGroups:
+              default:
+                replicas: 3
+                config:
+                  autoscaling:
+                    maxReplicas: 5
+            ",
+        );
+
+        assert_eq!(None, build_statefulset(&edc).spec.unwrap().replicas);
+    }
+
+    #[test]
+    fn test_probes() {
+        let edc = edc_cluster(
+            "
+            config:
+              probes:
+                startup:
+                  failureThreshold: 60
+            roleGroups:
+              default:
+                replicas: 1
+                config:
+                  probes:
+                    startup:
+                      periodSeconds: 5
+            ",
+        );
+
+        let container = build_statefulset(&edc)
+            .spec
+            .unwrap()
+            .template
+            .spec
+            .unwrap()
+            .containers
+            .into_iter()
+            .find(|container| container.name == APP_NAME)
+            .unwrap();
+
+        let startup_probe = container.startup_probe.unwrap();
+        assert_eq!(
+            Some("/api/check/startup"),
+            startup_probe
+                .http_get
+                .as_ref()
+                .and_then(|http_get| http_get.path.as_deref())
+        );
+        assert_eq!(Some(5), startup_probe.period_seconds);
+        assert_eq!(Some(60), startup_probe.failure_threshold);
+        assert_eq!(Some(5), startup_probe.timeout_seconds);
+
+        let readiness_probe = container.readiness_probe.unwrap();
+        let http_get = readiness_probe.http_get.unwrap();
+        assert_eq!(Some("/api/check/readiness"), http_get.path.as_deref());
+        assert_eq!(Some("HTTP"), http_get.scheme.as_deref());
+        assert_eq!(Some(3), readiness_probe.failure_threshold);
+
+        assert_eq!(
+            Some("/api/check/liveness".to_string()),
+            container
+                .liveness_probe
+                .and_then(|probe| probe.http_get)
+                .and_then(|http_get| http_get.path)
+        );
+    }
+
+    fn heap_jvm_args(memory: &str) -> Result<Vec<String>> {
+        let edc = edc_cluster(&format!(
+            "
+            roleGroups:
+              default:
+                replicas: 1
+                config:
+                  resources:
+                    memory: {memory}
+            "
+        ));
+        let merged_config = edc
+            .merged_config(&EDCRole::Connector, "default")
+            .expect("failed to merge the config");
+
+        construct_heap_jvm_args(&merged_config)
+    }
+
+    #[test]
+    fn test_heap_from_memory_limit() {
+        assert_eq!(
+            vec!["-Xmx1536m".to_string(), "-Xms1536m".to_string()],
+            heap_jvm_args("{limit: 2Gi}").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_heap_from_runtime_limits() {
+        // runtimeLimits take precedence over the heap derived from the memory limit
+        assert_eq!(
+            vec!["-Xmx3072m".to_string(), "-Xms1024m".to_string()],
+            heap_jvm_args("{limit: 4Gi, runtimeLimits: {max: 3Gi, min: 1Gi}}").unwrap()
+        );
+        assert_eq!(
+            vec!["-Xmx1536m".to_string(), "-Xms512m".to_string()],
+            heap_jvm_args("{limit: 2Gi, runtimeLimits: {min: 512Mi}}").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_heap_exceeds_memory_limit() {
+        for (memory, max) in [
+            ("{limit: 2Gi, runtimeLimits: {max: 3Gi, min: 1Gi}}", "3Gi"),
+            ("{limit: 2Gi, runtimeLimits: {max: 1537Mi}}", "1537Mi"),
+            ("{limit: 512Mi, runtimeLimits: {max: 256Mi}}", "256Mi"),
+        ] {
+            let error = heap_jvm_args(memory).unwrap_err();
+            assert!(
+                matches!(
+                    &error,
+                    Error::HeapExceedsMemoryLimit { max: heap_max, .. }
+                        if *heap_max == Quantity(max.to_string())
+                ),
+                "unexpected error: {error:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_heap_min_above_max() {
+        for memory in [
+            "{limit: 4Gi, runtimeLimits: {max: 1Gi, min: 2Gi}}",
+            "{limit: 2Gi, runtimeLimits: {min: 2Gi}}",
+        ] {
+            let error = heap_jvm_args(memory).unwrap_err();
+            assert!(
+                matches!(&error, Error::HeapMinAboveMax { min, .. } if min.0 == "2Gi"),
+                "unexpected error: {error:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_insufficient_memory_for_heap() {
+        for limit in ["512Mi", "256Mi"] {
+            let error = heap_jvm_args(&format!("{{limit: {limit}}}")).unwrap_err();
+            assert!(
+                matches!(
+                    &error,
+                    Error::InsufficientMemoryForHeap { memory_limit }
+                        if *memory_limit == Quantity(limit.to_string())
+                ),
+                "unexpected error: {error:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_metrics() {
+        for metrics_enabled in [true, false] {
+            let edc = edc_cluster_with_spec(
+                &format!("clusterConfig: {{metrics: {{enabled: {metrics_enabled}}}}}"),
+                "roleGroups: {default: {replicas: 1}}",
+            );
+            let resolved_product_image =
+                edc.spec.image.resolve(DOCKER_IMAGE_BASE_NAME, "0.0.0-dev");
+            let rolegroup_ref = edc.server_rolegroup_ref("default");
+            let merged_config = edc
+                .merged_config(&EDCRole::Connector, &rolegroup_ref.role_group)
+                .expect("failed to merge the config");
+
+            let config_map = build_connector_rolegroup_config_map(
+                &edc,
+                &resolved_product_image,
+                &rolegroup_ref,
+                &HashMap::new(),
+                &merged_config,
+                None,
+                None,
+            )
+            .expect("failed to build the ConfigMap");
+            let container = build_statefulset(&edc)
+                .spec
+                .and_then(|spec| spec.template.spec)
+                .and_then(|pod_spec| {
+                    pod_spec
+                        .containers
+                        .into_iter()
+                        .find(|container| container.name == APP_NAME)
+                })
+                .expect("the connector container exists");
+            let service = build_rolegroup_service(&edc, &resolved_product_image, &rolegroup_ref)
+                .expect("failed to build the rolegroup Service");
+
+            let exporter_config = config_map
+                .data
+                .unwrap_or_default()
+                .get(JMX_EXPORTER_CONFIG)
+                .cloned();
+            let javaagent = format!(
+                "-javaagent:{STACKABLE_JMX_DIR}/jmx_prometheus_javaagent.jar=9404:{STACKABLE_CONFIG_DIR}/{JMX_EXPORTER_CONFIG}"
+            );
+            let has_javaagent = container
+                .args
+                .unwrap_or_default()
+                .iter()
+                .any(|args| args.split(' ').any(|arg| arg == javaagent));
+            let metrics_container_port = container
+                .ports
+                .unwrap_or_default()
+                .into_iter()
+                .find(|port| port.name.as_deref() == Some(METRICS_PORT_NAME))
+                .map(|port| port.container_port);
+            let prometheus_port = service
+                .metadata
+                .annotations
+                .unwrap_or_default()
+                .get("prometheus.io/port")
+                .cloned();
+
+            if metrics_enabled {
+                assert_eq!(Some(JMX_EXPORTER_CONFIG_YAML.to_string()), exporter_config);
+                assert!(has_javaagent, "the javaagent is missing");
+                assert_eq!(Some(9404), metrics_container_port);
+                assert_eq!(Some("9404".to_string()), prometheus_port);
+            } else {
+                assert_eq!(None, exporter_config);
+                assert!(!has_javaagent, "the javaagent must not be loaded");
+                assert_eq!(None, metrics_container_port);
+                assert_eq!(None, prometheus_port);
+            }
+        }
+    }
+
+    #[test]
+    fn test_error_backoff() {
+        assert_eq!(Duration::from_secs(5), error_backoff(1));
+        assert_eq!(Duration::from_secs(10), error_backoff(2));
+        assert_eq!(Duration::from_secs(160), error_backoff(6));
+        assert_eq!(ERROR_BACKOFF_CAP, error_backoff(7));
+        assert_eq!(ERROR_BACKOFF_CAP, error_backoff(u32::MAX));
+    }
+
+    #[test]
+    fn test_failure_action() {
+        let reconcile_failures = Mutex::default();
+        let edc = ObjectRef::new("provider").within("default");
+
+        assert_eq!(
+            Action::requeue(Duration::from_secs(5)),
+            failure_action(
+                &reconcile_failures,
+                edc.clone(),
+                &Error::ObjectHasNoNamespace
+            )
+        );
+        assert_eq!(
+            Action::requeue(Duration::from_secs(10)),
+            failure_action(
+                &reconcile_failures,
+                edc.clone(),
+                &Error::ObjectHasNoNamespace
+            )
+        );
+
+        // A configuration error resets the backoff
+        assert_eq!(
+            Action::await_change(),
+            failure_action(&reconcile_failures, edc.clone(), &Error::NoServerRole)
+        );
+        assert!(reconcile_failures.lock().unwrap().is_empty());
+        assert_eq!(
+            Action::requeue(Duration::from_secs(5)),
+            failure_action(&reconcile_failures, edc, &Error::ObjectHasNoNamespace)
+        );
+    }
+
+    /// Directory with the EDCCluster fixtures in `fixtures` and the golden files rendered from
+    /// them in `golden`
+    const TEST_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/test");
+    /// If this environment variable is set, then the golden files are regenerated instead of
+    /// compared, e.g. `UPDATE_GOLDEN_FILES=1 cargo test`
+    const UPDATE_GOLDEN_FILES: &str = "UPDATE_GOLDEN_FILES";
+
+    /// Compare the given content with the golden file or overwrite the golden file in update mode
+    ///
+    /// YAML golden files are compared structurally, so that they can be written by hand.
+    fn assert_golden_file(path: &std::path::Path, actual: &str) {
+        if std::env::var_os(UPDATE_GOLDEN_FILES).is_some() {
+            std::fs::create_dir_all(path.parent().expect("golden files are in a directory"))
+                .expect("failed to create the golden file directory");
+            std::fs::write(path, actual).expect("failed to write the golden file");
+            return;
+        }
+
+        let expected = std::fs::read_to_string(path).unwrap_or_else(|error| {
+            panic!(
+                "failed to read the golden file [{}], run the tests with {UPDATE_GOLDEN_FILES}=1 to create it: {error}",
+                path.display()
+            )
+        });
+        if path
+            .extension()
+            .is_some_and(|extension| extension == "yaml")
+        {
+            assert_eq!(
+                serde_yaml::from_str::<serde_yaml::Value>(&expected).unwrap(),
+                serde_yaml::from_str::<serde_yaml::Value>(actual).unwrap(),
+                "the rendered resources differ from the golden file [{}], run the tests with {UPDATE_GOLDEN_FILES}=1 to update it",
+                path.display()
+            );
+        } else {
+            assert_eq!(
+                expected,
+                actual,
+                "the rendered content differs from the golden file [{}], run the tests with {UPDATE_GOLDEN_FILES}=1 to update it",
+                path.display()
+            );
+        }
+    }
+
+    fn product_config() -> ProductConfigManager {
+        ProductConfigManager::from_yaml_file(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../../deploy/config-spec/properties.yaml"
+        ))
+        .expect("invalid product config")
+    }
+
+    /// Build all resources of the given fixture and compare the generated configuration files,
+    /// the container args and the Services with the golden files of the fixture
+    fn assert_fixture_matches_golden_files(fixture: &str) {
+        let test_dir = std::path::Path::new(TEST_DIR);
+        let edc: EDCCluster = serde_yaml::from_str(
+            &std::fs::read_to_string(test_dir.join("fixtures").join(format!("{fixture}.yaml")))
+                .expect("failed to read the fixture"),
+        )
+        .expect("invalid fixture");
+        let golden_dir = test_dir.join("golden").join(fixture);
+
+        let product_config = product_config();
+        let resolved_product_image = edc.spec.image.resolve(DOCKER_IMAGE_BASE_NAME, "0.0.0-dev");
+        let s3_conn = crate::render::resolve_s3_connection(&edc, &[], &[])
+            .expect("the fixture must define the S3 connection inline");
+        let validated_config = validated_product_config(
+            &edc,
+            &resolved_product_image.product_version,
+            &product_config,
+        )
+        .expect("failed to validate the product config");
+        let server_config = validated_config
+            .get(&EDCRole::Connector.to_string())
+            .cloned()
+            .unwrap_or_default();
+
+        let mut services = vec![build_server_role_service(&edc, &resolved_product_image)
+            .expect("failed to build the role Service")];
+
+        for (rolegroup_name, rolegroup_config) in server_config.iter().collect::<BTreeMap<_, _>>() {
+            let rolegroup = edc.server_rolegroup_ref(rolegroup_name);
+            let rolegroup_dir = golden_dir.join(rolegroup_name);
+            let merged_config = edc
+                .merged_config(&EDCRole::Connector, rolegroup_name)
+                .expect("failed to merge the config");
+
+            services.push(
+                build_rolegroup_service(&edc, &resolved_product_image, &rolegroup)
+                    .expect("failed to build the rolegroup Service"),
+            );
+
+            let config_map = build_connector_rolegroup_config_map(
+                &edc,
+                &resolved_product_image,
+                &rolegroup,
+                rolegroup_config,
+                &merged_config,
+                s3_conn.as_ref(),
+                Some("vector-aggregator:6000"),
+            )
+            .expect("failed to build the ConfigMap");
+            let data = config_map.data.clone().unwrap_or_default();
+            for file in [
+                CONFIG_PROPERTIES,
+                LOGGING_PROPERTIES,
+                product_logging::framework::VECTOR_CONFIG_FILE,
+            ] {
+                match data.get(file) {
+                    Some(content) => assert_golden_file(&rolegroup_dir.join(file), content),
+                    None => assert!(
+                        !rolegroup_dir.join(file).exists(),
+                        "[{file}] of the rolegroup [{rolegroup_name}] is not rendered anymore, remove its golden file"
+                    ),
+                }
+            }
+
+            let statefulset = build_server_rolegroup_statefulset(
+                &edc,
+                &resolved_product_image,
+                &rolegroup,
+                rolegroup_config,
+                &merged_config,
+                s3_conn.as_ref(),
+                "edc-serviceaccount",
+                &compute_config_hash(&config_map, &[]),
+            )
+            .expect("failed to build the StatefulSet");
+            let container_args = statefulset
+                .spec
+                .and_then(|spec| spec.template.spec)
+                .and_then(|pod_spec| {
+                    pod_spec
+                        .containers
+                        .into_iter()
+                        .find(|container| container.name == APP_NAME)
+                })
+                .and_then(|container| container.args)
+                .expect("the connector container has args");
+            assert_golden_file(
+                &rolegroup_dir.join("args.txt"),
+                &format!("{}\n", container_args.join("\n")),
+            );
+        }
+
+        assert_golden_file(
+            &golden_dir.join("services.yaml"),
+            &serde_yaml::to_string(&services).expect("failed to serialize the Services"),
+        );
+    }
+
+    #[test]
+    fn test_web_context_addresses() {
+        let edc = edc_cluster(
+            "
+            configOverrides:
+              config.properties:
+                web.http.protocol.path: /dsp/
+            roleGroups:
+              default:
+                replicas: 1
+              control:
+                configOverrides:
+                  config.properties:
+                    web.http.control.path: /internal/control
+                    edc.dsp.callback.address: https://provider.example.com/dsp
+            ",
+        );
+
+        let validated_config = validated_product_config(&edc, "0.1.2", &product_config())
+            .expect("failed to validate the product config");
+        let config_properties = |rolegroup_name: &str| {
+            validated_config[&EDCRole::Connector.to_string()][rolegroup_name]
+                [&PropertyNameKind::File(CONFIG_PROPERTIES.to_string())]
+                .clone()
+        };
+
+        let default = config_properties("default");
+        assert_eq!(
+            Some("http://provider:8282/dsp"),
+            default.get(EDC_DSP_CALLBACK_ADDRESS).map(String::as_str)
+        );
+        assert_eq!(
+            Some("http://provider:8283/control/token"),
+            default
+                .get(EDC_DATAPLANE_TOKEN_VALIDATION_ENDPOINT)
+                .map(String::as_str)
+        );
+        let control = config_properties("control");
+        assert_eq!(
+            Some("https://provider.example.com/dsp"),
+            control.get(EDC_DSP_CALLBACK_ADDRESS).map(String::as_str)
+        );
+        assert_eq!(
+            Some("http://provider:8283/internal/control/token"),
+            control
+                .get(EDC_DATAPLANE_TOKEN_VALIDATION_ENDPOINT)
+                .map(String::as_str)
+        );
+    }
+
+    #[test]
+    fn test_golden_files_minimal() {
+        assert_fixture_matches_golden_files("minimal");
+    }
+
+    #[test]
+    fn test_golden_files_full() {
+        assert_fixture_matches_golden_files("full");
+    }
+}
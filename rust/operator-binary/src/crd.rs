@@ -10,7 +10,7 @@ use stackable_operator::{
         cluster_operation::ClusterOperation,
         product_image_selection::ProductImage,
         resources::{
-            CpuLimitsFragment, MemoryLimitsFragment, NoRuntimeLimits, NoRuntimeLimitsFragment,
+            CpuLimitsFragment, JvmHeapLimits, JvmHeapLimitsFragment, MemoryLimitsFragment,
             PvcConfig, PvcConfigFragment, Resources, ResourcesFragment,
         },
         s3,
@@ -18,21 +18,34 @@ use stackable_operator::{
     config::{
         fragment,
         fragment::{Fragment, ValidationError},
-        merge::Merge,
+        merge::{Atomic, Merge},
+    },
+    k8s_openapi::{
+        api::{apps::v1::StatefulSet, networking::v1::NetworkPolicyPeer},
+        apiextensions_apiserver::pkg::apis::apiextensions::v1::CustomResourceDefinition,
+        apimachinery::pkg::api::resource::Quantity,
+    },
+    kube::{
+        core::{crd::merge_crds, ApiResource},
+        runtime::reflector::ObjectRef,
+        CustomResource, CustomResourceExt, ResourceExt,
     },
-    k8s_openapi::apimachinery::pkg::api::resource::Quantity,
-    kube::{runtime::reflector::ObjectRef, CustomResource, ResourceExt},
     product_config_utils::{self, Configuration},
     product_logging::{self, spec::Logging},
     role_utils::{Role, RoleGroupRef},
     schemars::{self, JsonSchema},
     status::condition::{ClusterCondition, HasStatusCondition},
+    time::Duration,
 };
 use strum::{Display, EnumIter};
 
 use crate::affinity::get_affinity;
 
+pub mod v1alpha2;
+
 pub const APP_NAME: &str = "edc";
+/// Transfers are usually short, but large files should not be aborted by a rolling update
+pub const DEFAULT_GRACEFUL_SHUTDOWN_TIMEOUT: Duration = Duration::from_minutes_unchecked(5);
 // directories
 pub const STACKABLE_SECRETS_DIR: &str = "/stackable/secrets";
 pub const STACKABLE_CONFIG_DIR: &str = "/stackable/config";
@@ -44,17 +57,19 @@ pub const STACKABLE_LOG_DIR_NAME: &str = "log";
 pub const STACKABLE_LOG_CONFIG_MOUNT_DIR: &str = "/stackable/mount/log-config";
 pub const STACKABLE_LOG_CONFIG_MOUNT_DIR_NAME: &str = "log-config-mount";
 pub const STACKABLE_CERTS_DIR: &str = "/stackable/certificates";
+pub const STACKABLE_JMX_DIR: &str = "/stackable/jmx";
 // config file names
 pub const CONFIG_PROPERTIES: &str = "config.properties";
 pub const LOGGING_PROPERTIES: &str = "logging.properties";
 pub const JVM_SECURITY_PROPERTIES: &str = "security.properties";
+pub const JMX_EXPORTER_CONFIG: &str = "jmx_exporter.yaml";
 // secret keys
 pub const STACKABLE_CERT_MOUNT_KEYSTORE: &str = "cert.pfx";
 pub const STACKABLE_CERT_MOUNT_VAULT: &str = "vault.properties";
 // config properties (sorted alphabetically)
-pub const EDC_API_AUTH_KEY: &str = "edc.api.auth.key";
 pub const EDC_DSP_CALLBACK_ADDRESS: &str = "edc.dsp.callback.address";
 pub const EDC_DATAPLANE_TOKEN_VALIDATION_ENDPOINT: &str = "edc.dataplane.token.validation.endpoint";
+pub const EDC_DATASOURCE_DEFAULT_URL: &str = "edc.datasource.default.url";
 pub const EDC_FS_CONFIG: &str = "edc.fs.config";
 pub const EDC_HOSTNAME: &str = "edc.hostname";
 pub const EDC_IDS_ID: &str = "edc.ids.id";
@@ -64,22 +79,9 @@ pub const EDC_IONOS_ENDPOINT: &str = "edc.ionos.endpoint";
 pub const EDC_KEYSTORE: &str = "edc.keystore";
 pub const EDC_PARTICIPANT_ID: &str = "edc.participant.id";
 pub const EDC_VAULT: &str = "edc.vault";
-pub const EDC_VAULT_CERTIFICATE: &str = "edc.vault.certificate";
-pub const EDC_VAULT_CLIENTID: &str = "edc.vault.clientid";
-pub const EDC_VAULT_NAME: &str = "edc.vault.name";
-pub const EDC_VAULT_TENANTID: &str = "edc.vault.tenantid";
 pub const EDC_VAULT_HASHICORP_URL: &str = "edc.vault.hashicorp.url";
-pub const EDC_VAULT_HASHICORP_TOKEN: &str = "edc.vault.hashicorp.token";
-pub const WEB_HTTP_PORT: &str = "web.http.port";
+pub const EDC_WEB_HTTPS_KEYSTORE_PATH: &str = "edc.web.https.keystore.path";
 pub const WEB_HTTP_PATH: &str = "web.http.path";
-pub const WEB_HTTP_MANAGEMENT_PORT: &str = "web.http.management.port";
-pub const WEB_HTTP_MANAGEMENT_PATH: &str = "web.http.management.path";
-pub const WEB_HTTP_PROTOCOL_PORT: &str = "web.http.protocol.port";
-pub const WEB_HTTP_PROTOCOL_PATH: &str = "web.http.protocol.path";
-pub const WEB_HTTP_PUBLIC_PORT: &str = "web.http.public.port";
-pub const WEB_HTTP_PUBLIC_PATH: &str = "web.http.public.path";
-pub const WEB_HTTP_CONTROL_PORT: &str = "web.http.control.port";
-pub const WEB_HTTP_CONTROL_PATH: &str = "web.http.control.path";
 // S3
 pub const SECRET_KEY_S3_ACCESS_KEY: &str = "accessKey";
 pub const SECRET_KEY_S3_SECRET_KEY: &str = "secretKey";
@@ -94,15 +96,101 @@ pub const PROTOCOL_PORT_NAME: &str = "protocol";
 pub const PROTOCOL_PORT: u16 = 8282;
 pub const PUBLIC_PORT_NAME: &str = "public";
 pub const PUBLIC_PORT: u16 = 8284;
+pub const METRICS_PORT_NAME: &str = "metrics";
+pub const METRICS_PORT: u16 = 9404;
+
+// web contexts
+pub const DEFAULT_WEB_CONTEXT: WebContext = WebContext {
+    port_name: HTTP_PORT_NAME,
+    port: HTTP_PORT,
+    port_property: "web.http.port",
+    path_property: WEB_HTTP_PATH,
+    default_path: "/api",
+    external: false,
+};
+pub const CONTROL_WEB_CONTEXT: WebContext = WebContext {
+    port_name: CONTROL_PORT_NAME,
+    port: CONTROL_PORT,
+    port_property: "web.http.control.port",
+    path_property: "web.http.control.path",
+    default_path: "/control",
+    external: false,
+};
+pub const MANAGEMENT_WEB_CONTEXT: WebContext = WebContext {
+    port_name: MANAGEMENT_PORT_NAME,
+    port: MANAGEMENT_PORT,
+    port_property: "web.http.management.port",
+    path_property: "web.http.management.path",
+    default_path: "/management",
+    external: false,
+};
+pub const PROTOCOL_WEB_CONTEXT: WebContext = WebContext {
+    port_name: PROTOCOL_PORT_NAME,
+    port: PROTOCOL_PORT,
+    port_property: "web.http.protocol.port",
+    path_property: "web.http.protocol.path",
+    default_path: "/protocol",
+    external: true,
+};
+pub const PUBLIC_WEB_CONTEXT: WebContext = WebContext {
+    port_name: PUBLIC_PORT_NAME,
+    port: PUBLIC_PORT,
+    port_property: "web.http.public.port",
+    path_property: "web.http.public.path",
+    default_path: "/public",
+    external: true,
+};
+/// All web contexts of the connector, each one is served on its own port
+pub const WEB_CONTEXTS: [WebContext; 5] = [
+    DEFAULT_WEB_CONTEXT,
+    CONTROL_WEB_CONTEXT,
+    MANAGEMENT_WEB_CONTEXT,
+    PROTOCOL_WEB_CONTEXT,
+    PUBLIC_WEB_CONTEXT,
+];
 
 // logging
 pub const _JAVA_LOGGING: &str = "java-logging.properties";
 pub const EDC_CONNECTOR_JAVA_LOG_FILE: &str = "logging.properties";
 
+/// A web context of the connector, see the `web.http.*` properties in the product config
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct WebContext {
+    /// Name of the container and Service port
+    pub port_name: &'static str,
+    pub port: u16,
+    /// Config property containing the port, it must not be overridden because the container
+    /// ports, Services, probes and the NetworkPolicy are built with the fixed port
+    pub port_property: &'static str,
+    /// Config property containing the path under which the context is served
+    pub path_property: &'static str,
+    /// Path under which the context is served if the property is not set
+    pub default_path: &'static str,
+    /// Whether the context is called by the other participants of the dataspace, the other
+    /// contexts are only called from within the Kubernetes cluster
+    pub external: bool,
+}
+
+impl WebContext {
+    /// The path under which the context is served according to the given connector config,
+    /// without a trailing slash
+    pub fn path<'a>(&'a self, connector_config: Option<&'a BTreeMap<String, String>>) -> &'a str {
+        connector_config
+            .and_then(|config| config.get(self.path_property))
+            .map(String::as_str)
+            .unwrap_or(self.default_path)
+            .trim_end_matches('/')
+    }
+}
+
 #[derive(Snafu, Debug)]
 pub enum Error {
-    #[snafu(display("no metastore role configuration provided"))]
-    MissingMetaStoreRole,
+    #[snafu(display("the EDCCluster has no [{role}] role, it must be defined in [connectors]"))]
+    MissingConnectorRole { role: String },
+    #[snafu(display(
+        "the [{role}] role of the EDCCluster has no rolegroup [{role_group}] in [connectors.roleGroups]"
+    ))]
+    MissingConnectorRoleGroup { role: String, role_group: String },
     #[snafu(display("fragment validation failure"))]
     FragmentValidationFailure { source: ValidationError },
 }
@@ -135,7 +223,32 @@ pub struct EDCClusterSpec {
     pub connectors: Option<Role<ConnectorConfigFragment>>,
 }
 
-#[derive(Clone, Debug, Deserialize, Eq, JsonSchema, PartialEq, Serialize)]
+/// All served versions of the `EDCCluster` CRD, [`EDCCluster`] is the storage version
+pub struct EDCClusterVersions;
+
+impl CustomResourceExt for EDCClusterVersions {
+    fn crd() -> CustomResourceDefinition {
+        merge_crds(
+            vec![EDCCluster::crd(), v1alpha2::EDCCluster::crd()],
+            &EDCCluster::api_resource().version,
+        )
+        .expect("the versions of the EDCCluster CRD only differ in their schema")
+    }
+
+    fn crd_name() -> &'static str {
+        EDCCluster::crd_name()
+    }
+
+    fn api_resource() -> ApiResource {
+        EDCCluster::api_resource()
+    }
+
+    fn shortnames() -> &'static [&'static str] {
+        EDCCluster::shortnames()
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, JsonSchema, PartialEq, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct EDCClusterConfig {
     /// Name of the Vector aggregator discovery ConfigMap.
@@ -159,6 +272,52 @@ pub struct EDCClusterConfig {
     pub cert_secret: String,
 
     pub ionos: Ionos,
+
+    /// Prometheus metrics of the connectors, disabled by default.
+    #[serde(default)]
+    pub metrics: MetricsConfig,
+
+    /// NetworkPolicy which restricts the traffic of the connectors, disabled by default.
+    #[serde(default)]
+    pub network_policy: NetworkPolicyConfig,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Eq, JsonSchema, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MetricsConfig {
+    /// Expose the JVM metrics of the connectors in the Prometheus format on the `metrics` port.
+    /// The metrics are collected by the JMX exporter javaagent and the rolegroup Services are
+    /// annotated to be scraped by Prometheus.
+    #[serde(default)]
+    pub enabled: bool,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, JsonSchema, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NetworkPolicyConfig {
+    /// Create a NetworkPolicy which denies all traffic of the connectors except the one allowed
+    /// below and DNS lookups.
+    #[serde(default)]
+    pub enabled: bool,
+    /// Sources which may call the `protocol` and `public` APIs, e.g. the IP blocks of the other
+    /// participants of the dataspace. The APIs can be called from everywhere if no source is
+    /// given.
+    #[serde(default)]
+    pub external_sources: Vec<NetworkPolicyPeer>,
+    /// Namespaces and Pods which may call the `management`, `control` and observability APIs and
+    /// scrape the metrics. Only `namespaceSelector` and `podSelector` are allowed. The APIs
+    /// cannot be called by anyone if no source is given, except for the observability API, which
+    /// the namespace of the operator may always call for its health checks.
+    #[serde(default)]
+    pub management_sources: Vec<NetworkPolicyPeer>,
+    /// Destinations of the vault, which is called on the port of the `edc.vault.hashicorp.url`.
+    /// All destinations are allowed if none is given.
+    #[serde(default)]
+    pub vault_destinations: Vec<NetworkPolicyPeer>,
+    /// Destinations of the S3 storage, which is called on the port of the S3 connection. All
+    /// destinations are allowed if none is given.
+    #[serde(default)]
+    pub s3_destinations: Vec<NetworkPolicyPeer>,
 }
 
 #[derive(Clone, Debug, Deserialize, Eq, JsonSchema, PartialEq, Serialize)]
@@ -255,11 +414,173 @@ pub struct ConnectorStorageConfig {
 )]
 pub struct ConnectorConfig {
     #[fragment_attrs(serde(default))]
-    pub resources: Resources<ConnectorStorageConfig, NoRuntimeLimits>,
+    pub resources: Resources<ConnectorStorageConfig, JvmHeapLimits>,
     #[fragment_attrs(serde(default))]
     pub logging: Logging<Container>,
     #[fragment_attrs(serde(default))]
     pub affinity: StackableAffinity,
+    /// Time period the connector Pods have to finish their active transfers before they are
+    /// killed, e.g. `5m` or `1h`.
+    #[fragment_attrs(serde(default))]
+    pub graceful_shutdown_timeout: Option<Duration>,
+    /// Arguments which are added to or removed from the JVM command line of the connector.
+    /// Overrides on the rolegroup level replace the ones on the role level.
+    #[fragment_attrs(serde(default))]
+    pub jvm_argument_overrides: Option<JvmArgumentOverrides>,
+    /// Timings of the probes of the connector container, which check the observability API of the
+    /// connector.
+    #[fragment_attrs(serde(default))]
+    pub probes: ConnectorProbes,
+    /// Scale the connectors of the rolegroup with a HorizontalPodAutoscaler instead of running
+    /// the fixed number of `replicas`.
+    #[fragment_attrs(serde(default))]
+    pub autoscaling: Option<AutoscalingConfig>,
+    /// User and group ids of the connector Pods.
+    #[fragment_attrs(serde(default))]
+    pub security_context: ConnectorSecurityContext,
+}
+
+#[derive(Clone, Debug, Default, Fragment, JsonSchema, PartialEq)]
+#[fragment_attrs(
+    derive(
+        Clone,
+        Debug,
+        Default,
+        Deserialize,
+        Merge,
+        JsonSchema,
+        PartialEq,
+        Serialize
+    ),
+    serde(rename_all = "camelCase")
+)]
+pub struct ConnectorProbes {
+    /// Probe against `/check/startup`, the other probes start after it succeeded.
+    #[fragment_attrs(serde(default))]
+    pub startup: ProbeTimings,
+    /// Probe against `/check/readiness`, a connector only receives traffic while it is ready.
+    #[fragment_attrs(serde(default))]
+    pub readiness: ProbeTimings,
+    /// Probe against `/check/liveness`, a connector is restarted if it fails.
+    #[fragment_attrs(serde(default))]
+    pub liveness: ProbeTimings,
+}
+
+#[derive(Clone, Debug, Default, Fragment, JsonSchema, PartialEq)]
+#[fragment_attrs(
+    derive(
+        Clone,
+        Debug,
+        Default,
+        Deserialize,
+        Merge,
+        JsonSchema,
+        PartialEq,
+        Serialize
+    ),
+    serde(rename_all = "camelCase")
+)]
+pub struct ProbeTimings {
+    /// How often (in seconds) the probe is performed.
+    pub period_seconds: i32,
+    /// Number of seconds after which the probe times out.
+    pub timeout_seconds: i32,
+    /// Number of consecutive failures after which the probe is considered failed.
+    pub failure_threshold: i32,
+}
+
+#[derive(Clone, Debug, Default, Fragment, JsonSchema, PartialEq)]
+#[fragment_attrs(
+    derive(
+        Clone,
+        Debug,
+        Default,
+        Deserialize,
+        Merge,
+        JsonSchema,
+        PartialEq,
+        Serialize
+    ),
+    serde(rename_all = "camelCase")
+)]
+pub struct ConnectorSecurityContext {
+    /// User id of the containers, which must not be 0 because the containers run as non-root.
+    pub run_as_user: i64,
+    /// Primary group id of the containers.
+    pub run_as_group: i64,
+    /// Group id which owns the mounted volumes.
+    pub fs_group: i64,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Eq, JsonSchema, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JvmArgumentOverrides {
+    /// JVM arguments which are appended to the ones generated by the operator.
+    #[serde(default)]
+    pub add: Vec<String>,
+    /// JVM arguments generated by the operator which are removed, e.g. `-Xms1536m`.
+    #[serde(default)]
+    pub remove: Vec<String>,
+}
+
+impl Atomic for JvmArgumentOverrides {}
+
+impl JvmArgumentOverrides {
+    /// Remove and add the configured arguments from and to the given JVM arguments
+    pub fn apply(&self, jvm_args: Vec<String>) -> Vec<String> {
+        jvm_args
+            .into_iter()
+            .filter(|arg| !self.remove.contains(arg))
+            .chain(self.add.iter().cloned())
+            .collect()
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, JsonSchema, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AutoscalingConfig {
+    /// Number of connectors which are kept running at least.
+    #[serde(default = "AutoscalingConfig::default_min_replicas")]
+    pub min_replicas: u16,
+    /// Number of connectors which are started at most.
+    pub max_replicas: u16,
+    /// Metric which is kept at the given target by adding or removing connectors, an average CPU
+    /// utilization of 80% by default.
+    #[serde(default)]
+    pub target: AutoscalingTarget,
+}
+
+impl Atomic for AutoscalingConfig {}
+
+impl AutoscalingConfig {
+    fn default_min_replicas() -> u16 {
+        1
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, JsonSchema, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum AutoscalingTarget {
+    /// Average CPU utilization of the connectors in percent of their requested CPU.
+    CpuUtilization(u16),
+    /// Average value of a custom metric of the connector Pods, which must be served by an adapter
+    /// of the custom metrics API.
+    PodsMetric(PodsMetricTarget),
+}
+
+impl Default for AutoscalingTarget {
+    fn default() -> Self {
+        Self::CpuUtilization(80)
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, JsonSchema, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PodsMetricTarget {
+    /// Name of the metric, e.g. `edc_active_transfers`.
+    pub name: String,
+    /// Average value of the metric over all connectors, e.g. `10`.
+    pub average_value: Quantity,
 }
 
 impl ConnectorConfig {
@@ -272,7 +593,10 @@ impl ConnectorConfig {
                 },
                 memory: MemoryLimitsFragment {
                     limit: Some(Quantity("2Gi".to_owned())),
-                    runtime_limits: NoRuntimeLimitsFragment {},
+                    runtime_limits: JvmHeapLimitsFragment {
+                        max: None,
+                        min: None,
+                    },
                 },
                 storage: ConnectorStorageConfigFragment {
                     data: PvcConfigFragment {
@@ -284,6 +608,32 @@ impl ConnectorConfig {
             },
             logging: product_logging::spec::default_logging(),
             affinity: get_affinity(cluster_name, role),
+            graceful_shutdown_timeout: Some(DEFAULT_GRACEFUL_SHUTDOWN_TIMEOUT),
+            jvm_argument_overrides: None,
+            probes: ConnectorProbesFragment {
+                // Give the connector up to five minutes to boot its extensions
+                startup: ProbeTimingsFragment {
+                    period_seconds: Some(10),
+                    timeout_seconds: Some(5),
+                    failure_threshold: Some(30),
+                },
+                readiness: ProbeTimingsFragment {
+                    period_seconds: Some(10),
+                    timeout_seconds: Some(5),
+                    failure_threshold: Some(3),
+                },
+                liveness: ProbeTimingsFragment {
+                    period_seconds: Some(20),
+                    timeout_seconds: Some(5),
+                    failure_threshold: Some(3),
+                },
+            },
+            autoscaling: None,
+            security_context: ConnectorSecurityContextFragment {
+                run_as_user: Some(1000),
+                run_as_group: Some(0),
+                fs_group: Some(1000),
+            },
         }
     }
 }
@@ -302,6 +652,20 @@ impl Default for ServiceType {
     }
 }
 
+/// Computes the product configuration of the connectors.
+///
+/// The `config.properties` of a rolegroup are assembled with the following precedence, from lowest
+/// to highest:
+///
+/// 1. Operator defaults, i.e. the `recommendedValues` in `deploy/config-spec/properties.yaml`
+/// 2. Settings derived from the EDCCluster, computed in [`Configuration::compute_files`] and the
+///    resolved S3 connection
+/// 3. `configOverrides` on the role
+/// 4. `configOverrides` on the rolegroup
+///
+/// Properties which are not known to the operator can be set via `configOverrides` as well, except
+/// for the ports of the [`WEB_CONTEXTS`]. The addresses which depend on the paths of the web
+/// contexts are derived from the merged config, see [`crate::controller::validated_product_config`].
 impl Configuration for ConnectorConfigFragment {
     type Configurable = EDCCluster;
 
@@ -335,54 +699,12 @@ impl Configuration for ConnectorConfigFragment {
 
         let mut result = BTreeMap::new();
 
+        // Only settings derived from the EDCCluster are computed here, the static defaults (ports,
+        // paths, vault settings) are recommended values in the product config.
         if file == CONFIG_PROPERTIES {
             result.insert(EDC_HOSTNAME.to_owned(), Some(name.to_owned()));
             result.insert(EDC_IDS_ID.to_owned(), Some(format!("urn:connector:{name}")));
             result.insert(EDC_PARTICIPANT_ID.to_owned(), Some(name.to_owned()));
-            result.insert(
-                EDC_DSP_CALLBACK_ADDRESS.to_owned(),
-                Some(format!("http://{name}:{PROTOCOL_PORT}/protocol")),
-            );
-            // This is the password you need to pass in to the API via a header
-            // It should probably be set in a secret
-            result.insert(EDC_API_AUTH_KEY.to_owned(), Some("password".to_owned()));
-            // Ports
-            result.insert(WEB_HTTP_PORT.to_owned(), Some(HTTP_PORT.to_string()));
-            result.insert(WEB_HTTP_PATH.to_owned(), Some("/api".to_owned()));
-            result.insert(
-                WEB_HTTP_CONTROL_PORT.to_owned(),
-                Some(CONTROL_PORT.to_string()),
-            );
-            result.insert(
-                WEB_HTTP_CONTROL_PATH.to_owned(),
-                Some("/control".to_owned()),
-            );
-            result.insert(
-                WEB_HTTP_MANAGEMENT_PORT.to_owned(),
-                Some(MANAGEMENT_PORT.to_string()),
-            );
-            result.insert(
-                WEB_HTTP_MANAGEMENT_PATH.to_owned(),
-                Some("/management".to_owned()),
-            );
-            result.insert(
-                WEB_HTTP_PROTOCOL_PORT.to_owned(),
-                Some(PROTOCOL_PORT.to_string()),
-            );
-            result.insert(
-                WEB_HTTP_PROTOCOL_PATH.to_owned(),
-                Some("/protocol".to_owned()),
-            );
-            result.insert(
-                WEB_HTTP_PUBLIC_PORT.to_owned(),
-                Some(PUBLIC_PORT.to_string()),
-            );
-            result.insert(WEB_HTTP_PUBLIC_PATH.to_owned(), Some("/public".to_owned()));
-
-            result.insert(
-                EDC_DATAPLANE_TOKEN_VALIDATION_ENDPOINT.to_owned(),
-                Some(format!("http://{}:{}/control/token", name, CONTROL_PORT)),
-            );
 
             // result.insert(
             //     EDC_RECEIVER_HTTP_ENDPOINT.to_owned(),
@@ -405,21 +727,6 @@ impl Configuration for ConnectorConfigFragment {
             //     Some("public-key".to_owned()),
             // );
 
-            result.insert(EDC_VAULT_CLIENTID.to_owned(), Some("company1".to_owned()));
-            result.insert(EDC_VAULT_TENANTID.to_owned(), Some("1".to_owned()));
-            result.insert(
-                EDC_VAULT_CERTIFICATE.to_owned(),
-                Some("./resources".to_owned()), // TODO
-            );
-            result.insert(EDC_VAULT_NAME.to_owned(), Some("ionos".to_owned()));
-            result.insert(
-                EDC_VAULT_HASHICORP_URL.to_owned(),
-                Some("http://consumer-vault:8200".to_owned()), // TODO probably also a CRD arg
-            );
-            result.insert(
-                EDC_VAULT_HASHICORP_TOKEN.to_owned(),
-                Some("dev-token".to_owned()), // TODO needs to be provided as a secret
-            );
             result.insert(
                 EDC_KEYSTORE.to_owned(),
                 Some(format!(
@@ -444,6 +751,30 @@ impl Configuration for ConnectorConfigFragment {
 #[serde(rename_all = "camelCase")]
 pub struct EDCClusterStatus {
     pub conditions: Vec<ClusterCondition>,
+    /// The `metadata.generation` of the EDCCluster which was reconciled last.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub observed_generation: Option<i64>,
+    /// The participant id of the connectors.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub participant_id: Option<String>,
+    /// The address which other connectors use to call back the connectors via the Dataspace
+    /// Protocol.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dsp_callback_address: Option<String>,
+    /// The URLs of the `control`, `management`, `protocol` and `public` APIs of the connectors.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub endpoints: BTreeMap<String, EndpointStatus>,
+}
+
+#[derive(Clone, Default, Debug, Deserialize, Eq, JsonSchema, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EndpointStatus {
+    /// The URL of the API within the Kubernetes cluster.
+    pub internal_url: String,
+    /// The URL of the API outside of the Kubernetes cluster. It is only set if the `listenerClass`
+    /// exposes the connectors and the external address is already assigned.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub external_url: Option<String>,
 }
 
 impl HasStatusCondition for EDCCluster {
@@ -465,6 +796,11 @@ impl EDCCluster {
         self.metadata.name.as_deref()
     }
 
+    /// Whether the connectors expose Prometheus metrics
+    pub fn metrics_enabled(&self) -> bool {
+        self.spec.cluster_config.metrics.enabled
+    }
+
     /// Metadata about a server rolegroup
     pub fn server_rolegroup_ref(&self, group_name: impl Into<String>) -> RoleGroupRef<EDCCluster> {
         RoleGroupRef {
@@ -474,31 +810,6 @@ impl EDCCluster {
         }
     }
 
-    /// List all pods expected to form the cluster
-    ///
-    /// We try to predict the pods here rather than looking at the current cluster state in order to
-    /// avoid instance churn.
-    pub fn pods(&self) -> Result<impl Iterator<Item = PodRef> + '_, NoNamespaceError> {
-        let ns = self.metadata.namespace.clone().context(NoNamespaceSnafu)?;
-        Ok(self
-            .spec
-            .connectors
-            .iter()
-            .flat_map(|role| &role.role_groups)
-            // Order rolegroups consistently, to avoid spurious downstream rewrites
-            .collect::<BTreeMap<_, _>>()
-            .into_iter()
-            .flat_map(move |(rolegroup_name, rolegroup)| {
-                let rolegroup_ref = self.server_rolegroup_ref(rolegroup_name);
-                let ns = ns.clone();
-                (0..rolegroup.replicas.unwrap_or(0)).map(move |i| PodRef {
-                    namespace: ns.clone(),
-                    role_group_service_name: rolegroup_ref.object_name(),
-                    pod_name: format!("{}-{}", rolegroup_ref.object_name(), i),
-                })
-            }))
-    }
-
     pub fn get_role(&self, role: &EDCRole) -> Option<&Role<ConnectorConfigFragment>> {
         match role {
             EDCRole::Connector => self.spec.connectors.as_ref(),
@@ -514,7 +825,10 @@ impl EDCCluster {
         // Initialize the result with all default values as baseline
         let conf_defaults = ConnectorConfig::default_config(&self.name_any(), role);
 
-        let role = self.get_role(role).context(MissingMetaStoreRoleSnafu)?;
+        let role_name = role.to_string();
+        let role = self
+            .get_role(role)
+            .with_context(|| MissingConnectorRoleSnafu { role: &role_name })?;
 
         // Retrieve role resource config
         let mut conf_role = role.config.config.to_owned();
@@ -524,7 +838,10 @@ impl EDCCluster {
             .role_groups
             .get(role_group)
             .map(|rg| rg.config.config.clone())
-            .unwrap_or_default();
+            .with_context(|| MissingConnectorRoleGroupSnafu {
+                role: &role_name,
+                role_group,
+            })?;
 
         // Merge more specific configs into default config
         // Hierarchy is:
@@ -548,6 +865,33 @@ pub struct PodRef {
 }
 
 impl PodRef {
+    /// List the Pods of the given rolegroup StatefulSet
+    ///
+    /// The Pods are counted from the status of the applied StatefulSet, so that also the Pods
+    /// of an autoscaled rolegroup are found.
+    pub fn from_statefulset(statefulset: &StatefulSet) -> Result<Vec<PodRef>, NoNamespaceError> {
+        let namespace = statefulset.namespace().context(NoNamespaceSnafu)?;
+        let name = statefulset.name_any();
+        let role_group_service_name = statefulset
+            .spec
+            .as_ref()
+            .map(|spec| spec.service_name.clone())
+            .unwrap_or_else(|| name.clone());
+        let replicas = statefulset
+            .status
+            .as_ref()
+            .map(|status| status.replicas)
+            .unwrap_or_default();
+
+        Ok((0..replicas)
+            .map(|i| PodRef {
+                namespace: namespace.clone(),
+                role_group_service_name: role_group_service_name.clone(),
+                pod_name: format!("{name}-{i}"),
+            })
+            .collect())
+    }
+
     pub fn fqdn(&self) -> String {
         format!(
             "{}.{}.{}.svc.cluster.local",
@@ -555,3 +899,357 @@ impl PodRef {
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use stackable_operator::{
+        k8s_openapi::api::core::v1::{NodeAffinity, PodAntiAffinity},
+        product_logging::spec::{ContainerLogConfig, ContainerLogConfigChoice, LogLevel},
+    };
+
+    use super::*;
+    use crate::test_fixtures::edc_cluster;
+
+    /// Log level of the given logger of the connector container
+    fn logger_level(config: &ConnectorConfig, logger: &str) -> Option<LogLevel> {
+        match config.logging.containers.get(&Container::Connector) {
+            Some(ContainerLogConfig {
+                choice: Some(ContainerLogConfigChoice::Automatic(log_config)),
+            }) => log_config.loggers.get(logger).map(|logger| logger.level),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_default_config() {
+        let edc = edc_cluster(
+            "roleGroups:
+  default:
+    replicas: 1",
+        );
+
+        let config = edc
+            .merged_config(&EDCRole::Connector, "default")
+            .expect("failed to merge the config");
+
+        let resources = &config.resources;
+        assert_eq!(Some(Quantity("200m".to_owned())), resources.cpu.min);
+        assert_eq!(Some(Quantity("4".to_owned())), resources.cpu.max);
+        assert_eq!(Some(Quantity("2Gi".to_owned())), resources.memory.limit);
+        assert_eq!(None, resources.memory.runtime_limits.max);
+        assert_eq!(None, resources.memory.runtime_limits.min);
+        assert_eq!(
+            Some(Quantity("2Gi".to_owned())),
+            resources.storage.data.capacity
+        );
+        assert_eq!(None, resources.storage.data.storage_class);
+
+        assert!(!config.logging.enable_vector_agent);
+        for container in [Container::Connector, Container::Vector] {
+            assert!(matches!(
+                config.logging.containers.get(&container),
+                Some(ContainerLogConfig {
+                    choice: Some(ContainerLogConfigChoice::Automatic(_))
+                })
+            ));
+        }
+        assert_eq!(Some(LogLevel::INFO), logger_level(&config, "ROOT"));
+
+        assert_eq!(None, config.affinity.pod_affinity);
+        assert_eq!(
+            get_affinity("provider", &EDCRole::Connector).pod_anti_affinity,
+            config.affinity.pod_anti_affinity
+        );
+        assert_eq!(None, config.affinity.node_affinity);
+
+        assert_eq!(
+            Some(DEFAULT_GRACEFUL_SHUTDOWN_TIMEOUT),
+            config.graceful_shutdown_timeout
+        );
+        assert_eq!(None, config.jvm_argument_overrides);
+
+        let probes = &config.probes;
+        assert_eq!(
+            (10, 5, 30),
+            (
+                probes.startup.period_seconds,
+                probes.startup.timeout_seconds,
+                probes.startup.failure_threshold
+            )
+        );
+        assert_eq!(
+            (10, 5, 3),
+            (
+                probes.readiness.period_seconds,
+                probes.readiness.timeout_seconds,
+                probes.readiness.failure_threshold
+            )
+        );
+        assert_eq!(
+            (20, 5, 3),
+            (
+                probes.liveness.period_seconds,
+                probes.liveness.timeout_seconds,
+                probes.liveness.failure_threshold
+            )
+        );
+
+        assert_eq!(None, config.autoscaling);
+
+        let security_context = &config.security_context;
+        assert_eq!(
+            (1000, 0, 1000),
+            (
+                security_context.run_as_user,
+                security_context.run_as_group,
+                security_context.fs_group
+            )
+        );
+    }
+
+    #[test]
+    fn test_merge_resources() {
+        let edc = edc_cluster(
+            "config:
+  resources:
+    cpu:
+      max: '2'
+    memory:
+      limit: 3Gi
+    storage:
+      data:
+        capacity: 5Gi
+roleGroups:
+  default:
+    replicas: 1
+    config:
+      resources:
+        memory:
+          limit: 4Gi
+          runtimeLimits:
+            max: 2Gi
+  other:
+    replicas: 1",
+        );
+
+        let config = edc
+            .merged_config(&EDCRole::Connector, "default")
+            .expect("failed to merge the config");
+
+        // The rolegroup overrides the role, which overrides the defaults
+        assert_eq!(Some(Quantity("200m".to_owned())), config.resources.cpu.min);
+        assert_eq!(Some(Quantity("2".to_owned())), config.resources.cpu.max);
+        assert_eq!(
+            Some(Quantity("4Gi".to_owned())),
+            config.resources.memory.limit
+        );
+        assert_eq!(
+            Some(Quantity("2Gi".to_owned())),
+            config.resources.memory.runtime_limits.max
+        );
+        assert_eq!(
+            Some(Quantity("5Gi".to_owned())),
+            config.resources.storage.data.capacity
+        );
+
+        // Other rolegroups are not affected
+        let config = edc
+            .merged_config(&EDCRole::Connector, "other")
+            .expect("failed to merge the config");
+        assert_eq!(
+            Some(Quantity("3Gi".to_owned())),
+            config.resources.memory.limit
+        );
+        assert_eq!(None, config.resources.memory.runtime_limits.max);
+    }
+
+    #[test]
+    fn test_merge_logging() {
+        let edc = edc_cluster(
+            "config:
+  logging:
+    enableVectorAgent: true
+    containers:
+      connector:
+        loggers:
+          org.eclipse.edc:
+            level: DEBUG
+          org.eclipse.jetty:
+            level: WARN
+roleGroups:
+  default:
+    replicas: 1
+    config:
+      logging:
+        containers:
+          connector:
+            loggers:
+              org.eclipse.edc:
+                level: TRACE",
+        );
+
+        let config = edc
+            .merged_config(&EDCRole::Connector, "default")
+            .expect("failed to merge the config");
+
+        assert!(config.logging.enable_vector_agent);
+        assert_eq!(Some(LogLevel::INFO), logger_level(&config, "ROOT"));
+        assert_eq!(
+            Some(LogLevel::TRACE),
+            logger_level(&config, "org.eclipse.edc")
+        );
+        assert_eq!(
+            Some(LogLevel::WARN),
+            logger_level(&config, "org.eclipse.jetty")
+        );
+    }
+
+    #[test]
+    fn test_merge_affinity() {
+        let edc = edc_cluster(
+            "config:
+  affinity:
+    nodeAffinity:
+      requiredDuringSchedulingIgnoredDuringExecution:
+        nodeSelectorTerms:
+          - matchExpressions:
+              - key: topology.kubernetes.io/zone
+                operator: In
+                values:
+                  - de-fra
+roleGroups:
+  default:
+    replicas: 1
+    config:
+      affinity:
+        podAntiAffinity:
+          requiredDuringSchedulingIgnoredDuringExecution:
+            - labelSelector:
+                matchLabels:
+                  app.kubernetes.io/name: edc
+              topologyKey: kubernetes.io/hostname",
+        );
+
+        let config = edc
+            .merged_config(&EDCRole::Connector, "default")
+            .expect("failed to merge the config");
+
+        let node_affinity: NodeAffinity = serde_yaml::from_str(
+            "
+requiredDuringSchedulingIgnoredDuringExecution:
+  nodeSelectorTerms:
+    - matchExpressions:
+        - key: topology.kubernetes.io/zone
+          operator: In
+          values:
+            - de-fra",
+        )
+        .unwrap();
+        let pod_anti_affinity: PodAntiAffinity = serde_yaml::from_str(
+            "
+requiredDuringSchedulingIgnoredDuringExecution:
+  - labelSelector:
+      matchLabels:
+        app.kubernetes.io/name: edc
+    topologyKey: kubernetes.io/hostname",
+        )
+        .unwrap();
+
+        assert_eq!(Some(node_affinity), config.affinity.node_affinity);
+        // The Kubernetes affinities are replaced as a whole instead of being merged
+        assert_eq!(Some(pod_anti_affinity), config.affinity.pod_anti_affinity);
+        assert_eq!(None, config.affinity.pod_affinity);
+    }
+
+    #[test]
+    fn test_jvm_argument_overrides_are_replaced() {
+        let edc = edc_cluster(
+            "config:
+  jvmArgumentOverrides:
+    add:
+      - -XX:+UseG1GC
+roleGroups:
+  default:
+    replicas: 1
+    config:
+      jvmArgumentOverrides:
+        remove:
+          - -Xms1536m
+  other:
+    replicas: 1",
+        );
+
+        let config = edc
+            .merged_config(&EDCRole::Connector, "default")
+            .expect("failed to merge the config");
+        assert_eq!(
+            Some(JvmArgumentOverrides {
+                add: vec![],
+                remove: vec!["-Xms1536m".to_owned()],
+            }),
+            config.jvm_argument_overrides
+        );
+
+        let config = edc
+            .merged_config(&EDCRole::Connector, "other")
+            .expect("failed to merge the config");
+        assert_eq!(
+            Some(JvmArgumentOverrides {
+                add: vec!["-XX:+UseG1GC".to_owned()],
+                remove: vec![],
+            }),
+            config.jvm_argument_overrides
+        );
+    }
+
+    #[test]
+    fn test_missing_role_and_rolegroup() {
+        let edc = edc_cluster("");
+        assert_eq!(
+            "the EDCCluster has no [server] role, it must be defined in [connectors]",
+            edc.merged_config(&EDCRole::Connector, "default")
+                .unwrap_err()
+                .to_string()
+        );
+
+        let edc = edc_cluster(
+            "roleGroups:
+  default:
+    replicas: 1",
+        );
+        assert!(matches!(
+            edc.merged_config(&EDCRole::Connector, "missing"),
+            Err(Error::MissingConnectorRoleGroup { role, role_group })
+                if role == "server" && role_group == "missing"
+        ));
+    }
+
+    #[test]
+    fn test_pods_from_statefulset() {
+        let statefulset: StatefulSet = serde_yaml::from_str(
+            "
+            metadata:
+              name: provider-server-default
+              namespace: default
+            spec:
+              replicas: 1
+              selector: {}
+              serviceName: provider-server-default
+              template: {}
+            status:
+              replicas: 2
+            ",
+        )
+        .expect("invalid test input");
+
+        let pods = PodRef::from_statefulset(&statefulset).expect("failed to build the Pod refs");
+
+        assert_eq!(
+            vec![
+                "provider-server-default-0.provider-server-default.default.svc.cluster.local",
+                "provider-server-default-1.provider-server-default.default.svc.cluster.local",
+            ],
+            pods.iter().map(PodRef::fqdn).collect::<Vec<_>>()
+        );
+    }
+}
@@ -0,0 +1,326 @@
+//! Version `v1alpha2` of the `EDCCluster`, which groups the settings of the cluster by concern.
+//!
+//! `v1alpha1` is still the storage version and the version used by the reconciler, objects of
+//! this version are converted by the conversion webhook.
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use snafu::{OptionExt, ResultExt, Snafu};
+use stackable_operator::{
+    commons::{cluster_operation::ClusterOperation, product_image_selection::ProductImage},
+    kube::CustomResource,
+    role_utils::Role,
+    schemars::{self, JsonSchema},
+};
+
+use super::{
+    ConnectorConfigFragment, CurrentlySupportedListenerClasses, EDCClusterStatus, Ionos,
+    MetricsConfig, NetworkPolicyConfig,
+};
+
+pub const API_VERSION_V1ALPHA1: &str = "edc.stackable.tech/v1alpha1";
+pub const API_VERSION_V1ALPHA2: &str = "edc.stackable.tech/v1alpha2";
+
+#[derive(Snafu, Debug)]
+pub enum Error {
+    #[snafu(display("the object has no apiVersion"))]
+    MissingApiVersion,
+    #[snafu(display("conversion from [{from}] to [{to}] is not supported"))]
+    UnsupportedConversion { from: String, to: String },
+    #[snafu(display("failed to parse the EDCCluster of version [{api_version}]"))]
+    ParseObject {
+        source: serde_json::Error,
+        api_version: String,
+    },
+    #[snafu(display("failed to serialize the converted EDCCluster"))]
+    SerializeObject { source: serde_json::Error },
+}
+
+#[derive(Clone, CustomResource, Debug, Deserialize, JsonSchema, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+#[kube(
+    group = "edc.stackable.tech",
+    version = "v1alpha2",
+    kind = "EDCCluster",
+    plural = "edcclusters",
+    shortname = "edc",
+    status = "EDCClusterStatus",
+    namespaced,
+    crates(
+        kube_core = "stackable_operator::kube::core",
+        k8s_openapi = "stackable_operator::k8s_openapi",
+        schemars = "stackable_operator::schemars"
+    )
+)]
+pub struct EDCClusterSpec {
+    /// General settings of the connectors
+    pub cluster_config: EDCClusterConfig,
+    /// Cluster operations like pause reconciliation or cluster stop.
+    #[serde(default)]
+    pub cluster_operation: ClusterOperation,
+    /// The EDC image to use.
+    pub image: ProductImage,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub connectors: Option<Role<ConnectorConfigFragment>>,
+}
+
+#[derive(Clone, Debug, Deserialize, JsonSchema, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EDCClusterConfig {
+    /// Identity of the connectors in the dataspace.
+    pub identity: Identity,
+    /// Storage of the transferred data.
+    pub storage: Storage,
+    /// How the APIs of the connectors are exposed.
+    #[serde(default)]
+    pub endpoints: Endpoints,
+    /// Name of the Vector aggregator discovery ConfigMap.
+    /// It must contain the key `ADDRESS` with the address of the Vector aggregator.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vector_aggregator_config_map_name: Option<String>,
+    /// Prometheus metrics of the connectors, disabled by default.
+    #[serde(default)]
+    pub metrics: MetricsConfig,
+}
+
+#[derive(Clone, Debug, Deserialize, Eq, JsonSchema, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Identity {
+    /// Name of the Secret with the certificate of the connectors, which is used for the vault and
+    /// the keystore.
+    pub cert_secret: String,
+}
+
+#[derive(Clone, Debug, Deserialize, Eq, JsonSchema, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Storage {
+    /// IONOS S3 storage, which is currently the only supported storage.
+    pub ionos: Ionos,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, JsonSchema, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Endpoints {
+    /// Determines how the Services of the connectors are exposed:
+    ///
+    /// * cluster-internal: Use a ClusterIP service
+    ///
+    /// * external-unstable: Use a NodePort service
+    ///
+    /// * external-stable: Use a LoadBalancer service
+    #[serde(default)]
+    pub listener_class: CurrentlySupportedListenerClasses,
+    /// NetworkPolicy which restricts the traffic of the connectors, disabled by default.
+    #[serde(default)]
+    pub network_policy: NetworkPolicyConfig,
+}
+
+impl From<super::EDCClusterSpec> for EDCClusterSpec {
+    fn from(spec: super::EDCClusterSpec) -> Self {
+        let cluster_config = spec.cluster_config;
+        Self {
+            cluster_config: EDCClusterConfig {
+                identity: Identity {
+                    cert_secret: cluster_config.cert_secret,
+                },
+                storage: Storage {
+                    ionos: cluster_config.ionos,
+                },
+                endpoints: Endpoints {
+                    listener_class: cluster_config.listener_class,
+                    network_policy: cluster_config.network_policy,
+                },
+                vector_aggregator_config_map_name: cluster_config.vector_aggregator_config_map_name,
+                metrics: cluster_config.metrics,
+            },
+            cluster_operation: spec.cluster_operation,
+            image: spec.image,
+            connectors: spec.connectors,
+        }
+    }
+}
+
+impl From<EDCClusterSpec> for super::EDCClusterSpec {
+    fn from(spec: EDCClusterSpec) -> Self {
+        let cluster_config = spec.cluster_config;
+        Self {
+            cluster_config: super::EDCClusterConfig {
+                vector_aggregator_config_map_name: cluster_config.vector_aggregator_config_map_name,
+                listener_class: cluster_config.endpoints.listener_class,
+                cert_secret: cluster_config.identity.cert_secret,
+                ionos: cluster_config.storage.ionos,
+                metrics: cluster_config.metrics,
+                network_policy: cluster_config.endpoints.network_policy,
+            },
+            cluster_operation: spec.cluster_operation,
+            image: spec.image,
+            connectors: spec.connectors,
+        }
+    }
+}
+
+/// Convert the EDCCluster object to the desired API version, the metadata and the status are
+/// taken over unchanged
+pub fn convert(object: Value, desired_api_version: &str) -> Result<Value, Error> {
+    let api_version = object
+        .get("apiVersion")
+        .and_then(Value::as_str)
+        .map(ToString::to_string)
+        .context(MissingApiVersionSnafu)?;
+
+    let converted = match (api_version.as_str(), desired_api_version) {
+        (from, to) if from == to => return Ok(object),
+        (API_VERSION_V1ALPHA1, API_VERSION_V1ALPHA2) => {
+            let edc =
+                serde_json::from_value::<super::EDCCluster>(object).context(ParseObjectSnafu {
+                    api_version: API_VERSION_V1ALPHA1,
+                })?;
+            serde_json::to_value(EDCCluster {
+                metadata: edc.metadata,
+                spec: edc.spec.into(),
+                status: edc.status,
+            })
+        }
+        (API_VERSION_V1ALPHA2, API_VERSION_V1ALPHA1) => {
+            let edc = serde_json::from_value::<EDCCluster>(object).context(ParseObjectSnafu {
+                api_version: API_VERSION_V1ALPHA2,
+            })?;
+            serde_json::to_value(super::EDCCluster {
+                metadata: edc.metadata,
+                spec: edc.spec.into(),
+                status: edc.status,
+            })
+        }
+        (from, to) => {
+            return UnsupportedConversionSnafu { from, to }.fail();
+        }
+    };
+
+    converted.context(SerializeObjectSnafu)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+
+    use super::*;
+    use crate::crd;
+
+    /// The EDCClusters of the demo, which are written against `v1alpha1`
+    fn demo_clusters() -> Vec<Value> {
+        serde_yaml::Deserializer::from_str(include_str!("../../../../demo/manifests/demo.yaml"))
+            .map(|document| Value::deserialize(document).expect("invalid demo manifest"))
+            .filter(|object| object["kind"] == "EDCCluster")
+            .collect()
+    }
+
+    #[test]
+    fn test_round_trip_from_v1alpha1() {
+        let clusters = demo_clusters();
+        assert!(!clusters.is_empty());
+
+        for object in clusters {
+            let v1alpha2 = convert(object.clone(), API_VERSION_V1ALPHA2).unwrap();
+            assert_eq!(API_VERSION_V1ALPHA2, v1alpha2["apiVersion"]);
+            assert_eq!(
+                object["spec"]["clusterConfig"]["certSecret"],
+                v1alpha2["spec"]["clusterConfig"]["identity"]["certSecret"]
+            );
+
+            let v1alpha1 = convert(v1alpha2, API_VERSION_V1ALPHA1).unwrap();
+            let original = serde_json::from_value::<crd::EDCCluster>(object).unwrap();
+            let round_tripped = serde_json::from_value::<crd::EDCCluster>(v1alpha1).unwrap();
+            assert_eq!(original.metadata, round_tripped.metadata);
+            assert_eq!(original.spec, round_tripped.spec);
+        }
+    }
+
+    #[test]
+    fn test_round_trip_from_v1alpha2() {
+        let object = serde_yaml::from_str::<Value>(
+            r#"
+            apiVersion: edc.stackable.tech/v1alpha2
+            kind: EDCCluster
+            metadata:
+              name: provider
+              namespace: default
+            spec:
+              image:
+                productVersion: 0.1.2
+              clusterConfig:
+                identity:
+                  certSecret: connector-cert
+                storage:
+                  ionos:
+                    tokenSecret: ionos-token
+                    s3:
+                      reference: source-bucket
+                endpoints:
+                  listenerClass: external-stable
+                  networkPolicy:
+                    enabled: true
+                    managementSources:
+                      - namespaceSelector:
+                          matchLabels:
+                            kubernetes.io/metadata.name: backend
+                metrics:
+                  enabled: true
+              connectors:
+                roleGroups:
+                  default:
+                    replicas: 1
+            status:
+              participantId: provider
+            "#,
+        )
+        .unwrap();
+
+        let v1alpha1 = convert(object.clone(), API_VERSION_V1ALPHA1).unwrap();
+        assert_eq!(
+            "external-stable",
+            v1alpha1["spec"]["clusterConfig"]["listenerClass"]
+        );
+        assert_eq!(
+            Some(true),
+            v1alpha1["spec"]["clusterConfig"]["networkPolicy"]["enabled"].as_bool()
+        );
+        assert_eq!("provider", v1alpha1["status"]["participantId"]);
+
+        let v1alpha2 = convert(v1alpha1, API_VERSION_V1ALPHA2).unwrap();
+        let original = serde_json::from_value::<EDCCluster>(object).unwrap();
+        let round_tripped = serde_json::from_value::<EDCCluster>(v1alpha2).unwrap();
+        assert_eq!(original.metadata, round_tripped.metadata);
+        assert_eq!(original.spec, round_tripped.spec);
+        assert_eq!(original.status, round_tripped.status);
+    }
+
+    #[test]
+    fn test_served_versions() {
+        use stackable_operator::kube::CustomResourceExt;
+
+        let crd = crd::EDCClusterVersions::crd();
+        let versions = crd
+            .spec
+            .versions
+            .iter()
+            .map(|version| (version.name.as_str(), version.served, version.storage))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            vec![("v1alpha1", true, true), ("v1alpha2", true, false)],
+            versions
+        );
+    }
+
+    #[test]
+    fn test_unsupported_conversion() {
+        let object = serde_json::json!({
+            "apiVersion": "edc.stackable.tech/v1beta1",
+            "kind": "EDCCluster",
+        });
+
+        assert!(matches!(
+            convert(object, API_VERSION_V1ALPHA1),
+            Err(Error::UnsupportedConversion { .. })
+        ));
+    }
+}
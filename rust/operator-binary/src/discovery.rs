@@ -0,0 +1,141 @@
+//! Discovery ConfigMap which other workloads, e.g. backend applications or a counterpart
+//! connector, can mount to find the connectors
+use snafu::{ResultExt, Snafu};
+use stackable_operator::{
+    builder::{configmap::ConfigMapBuilder, meta::ObjectMetaBuilder},
+    commons::product_image_selection::ResolvedProductImage,
+    k8s_openapi::api::core::v1::ConfigMap,
+};
+
+use crate::{
+    controller::build_recommended_labels,
+    crd::{
+        EDCCluster, EDCClusterStatus, EDCRole, MANAGEMENT_WEB_CONTEXT, PROTOCOL_WEB_CONTEXT,
+        PUBLIC_WEB_CONTEXT,
+    },
+};
+
+const DISCOVERY_PARTICIPANT_ID: &str = "EDC_PARTICIPANT_ID";
+const DISCOVERY_PROTOCOL_URL: &str = "EDC_PROTOCOL_URL";
+const DISCOVERY_MANAGEMENT_URL: &str = "EDC_MANAGEMENT_URL";
+const DISCOVERY_PUBLIC_URL: &str = "EDC_PUBLIC_URL";
+
+#[derive(Snafu, Debug)]
+pub enum Error {
+    #[snafu(display("object is missing metadata to build owner reference"))]
+    ObjectMissingMetadataForOwnerRef {
+        source: stackable_operator::builder::meta::Error,
+    },
+    #[snafu(display("failed to build object meta data"))]
+    ObjectMeta {
+        source: stackable_operator::builder::meta::Error,
+    },
+    #[snafu(display("failed to build ConfigMap"))]
+    BuildConfigMap {
+        source: stackable_operator::builder::configmap::Error,
+    },
+}
+
+type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// Build the discovery [`ConfigMap`], which is named after the EDCCluster and contains the
+/// participant id and the cluster-internal URLs of the Dataspace Protocol, the management API and
+/// the public data plane as published in the given status
+pub fn build_discovery_configmap(
+    edc: &EDCCluster,
+    resolved_product_image: &ResolvedProductImage,
+    status: &EDCClusterStatus,
+) -> Result<ConfigMap> {
+    let metadata = ObjectMetaBuilder::new()
+        .name_and_namespace(edc)
+        .ownerreference_from_resource(edc, None, Some(true))
+        .context(ObjectMissingMetadataForOwnerRefSnafu)?
+        .with_recommended_labels(build_recommended_labels(
+            edc,
+            &resolved_product_image.app_version_label,
+            &EDCRole::Connector.to_string(),
+            "discovery",
+        ))
+        .context(ObjectMetaSnafu)?
+        .build();
+
+    let mut cm_builder = ConfigMapBuilder::new();
+    cm_builder.metadata(metadata);
+
+    if let Some(participant_id) = &status.participant_id {
+        cm_builder.add_data(DISCOVERY_PARTICIPANT_ID, participant_id);
+    }
+
+    for (key, context) in [
+        (DISCOVERY_PROTOCOL_URL, PROTOCOL_WEB_CONTEXT),
+        (DISCOVERY_MANAGEMENT_URL, MANAGEMENT_WEB_CONTEXT),
+        (DISCOVERY_PUBLIC_URL, PUBLIC_WEB_CONTEXT),
+    ] {
+        if let Some(endpoint) = status.endpoints.get(context.port_name) {
+            cm_builder.add_data(key, &endpoint.internal_url);
+        }
+    }
+
+    cm_builder.build().context(BuildConfigMapSnafu)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::*;
+    use crate::{crd::EndpointStatus, test_fixtures::edc_cluster};
+
+    #[test]
+    fn test_discovery_configmap() {
+        let edc = edc_cluster(
+            "
+            roleGroups:
+              default:
+                replicas: 1
+            ",
+        );
+        let resolved_product_image = edc.spec.image.resolve("edc", "0.0.0-dev");
+        let status = EDCClusterStatus {
+            participant_id: Some("provider".to_string()),
+            endpoints: BTreeMap::from([
+                (
+                    "management".to_string(),
+                    EndpointStatus {
+                        internal_url: "http://provider.default.svc.cluster.local:8182/management"
+                            .to_string(),
+                        external_url: None,
+                    },
+                ),
+                (
+                    "protocol".to_string(),
+                    EndpointStatus {
+                        internal_url: "http://provider.default.svc.cluster.local:8282/protocol"
+                            .to_string(),
+                        external_url: Some("http://172.18.0.2:30282/protocol".to_string()),
+                    },
+                ),
+            ]),
+            ..EDCClusterStatus::default()
+        };
+
+        let config_map = build_discovery_configmap(&edc, &resolved_product_image, &status)
+            .expect("failed to build the discovery ConfigMap");
+
+        assert_eq!(Some("provider"), config_map.metadata.name.as_deref());
+        assert_eq!(
+            Some(BTreeMap::from([
+                (
+                    DISCOVERY_MANAGEMENT_URL.to_string(),
+                    "http://provider.default.svc.cluster.local:8182/management".to_string()
+                ),
+                (DISCOVERY_PARTICIPANT_ID.to_string(), "provider".to_string()),
+                (
+                    DISCOVERY_PROTOCOL_URL.to_string(),
+                    "http://provider.default.svc.cluster.local:8282/protocol".to_string()
+                ),
+            ])),
+            config_map.data
+        );
+    }
+}
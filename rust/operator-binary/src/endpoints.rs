@@ -0,0 +1,148 @@
+//! URLs of the APIs of the connectors, which are published in the status of the EDCCluster
+use std::collections::BTreeMap;
+
+use snafu::{OptionExt, ResultExt, Snafu};
+use stackable_operator::{
+    client::Client,
+    k8s_openapi::api::{
+        apps::v1::StatefulSet,
+        core::v1::{Pod, Service},
+    },
+    kube::ResourceExt,
+};
+
+use crate::crd::{
+    CurrentlySupportedListenerClasses, EDCCluster, EndpointStatus, PodRef, WebContext,
+    CONTROL_WEB_CONTEXT, EDC_WEB_HTTPS_KEYSTORE_PATH, MANAGEMENT_WEB_CONTEXT, PROTOCOL_WEB_CONTEXT,
+    PUBLIC_WEB_CONTEXT,
+};
+
+/// The web contexts which are published, the default context only serves the observability API
+const PUBLISHED_WEB_CONTEXTS: [WebContext; 4] = [
+    CONTROL_WEB_CONTEXT,
+    MANAGEMENT_WEB_CONTEXT,
+    PROTOCOL_WEB_CONTEXT,
+    PUBLIC_WEB_CONTEXT,
+];
+
+#[derive(Snafu, Debug)]
+pub enum Error {
+    #[snafu(display("object has no namespace"))]
+    ObjectHasNoNamespace,
+    #[snafu(display("failed to build the references to the connector Pods"))]
+    BuildPodRefs {
+        source: crate::crd::NoNamespaceError,
+    },
+    #[snafu(display("failed to retrieve the Pod [{pod}]"))]
+    RetrievePod {
+        source: stackable_operator::client::Error,
+        pod: String,
+    },
+}
+
+type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// Whether the connector serves its web contexts over HTTPS
+pub fn https_enabled(connector_config: Option<&BTreeMap<String, String>>) -> bool {
+    connector_config.is_some_and(|config| config.contains_key(EDC_WEB_HTTPS_KEYSTORE_PATH))
+}
+
+/// Build the internal and external URLs of the published web contexts, keyed by the name of the
+/// context
+///
+/// The internal URLs point to the role Service, the external ones are only known if the role
+/// Service is exposed by the `listenerClass` and Kubernetes already assigned an address.
+pub async fn build_endpoint_status(
+    client: &Client,
+    edc: &EDCCluster,
+    role_service: &Service,
+    statefulsets: &[StatefulSet],
+    connector_config: Option<&BTreeMap<String, String>>,
+) -> Result<BTreeMap<String, EndpointStatus>> {
+    let namespace = edc.namespace().context(ObjectHasNoNamespaceSnafu)?;
+    let scheme = if https_enabled(connector_config) {
+        "https"
+    } else {
+        "http"
+    };
+    let internal_host = format!("{}.{namespace}.svc.cluster.local", role_service.name_any());
+    let external_host = external_host(client, edc, role_service, statefulsets).await?;
+
+    Ok(PUBLISHED_WEB_CONTEXTS
+        .iter()
+        .map(|context| {
+            let path = context.path(connector_config);
+            let external_url = external_host
+                .as_deref()
+                .zip(external_port(edc, role_service, context))
+                .map(|(host, port)| format!("{scheme}://{host}:{port}{path}"));
+
+            (
+                context.port_name.to_string(),
+                EndpointStatus {
+                    internal_url: format!("{scheme}://{internal_host}:{}{path}", context.port),
+                    external_url,
+                },
+            )
+        })
+        .collect())
+}
+
+/// The host under which the role Service is reachable from outside of the Kubernetes cluster
+async fn external_host(
+    client: &Client,
+    edc: &EDCCluster,
+    role_service: &Service,
+    statefulsets: &[StatefulSet],
+) -> Result<Option<String>> {
+    match edc.spec.cluster_config.listener_class {
+        CurrentlySupportedListenerClasses::ClusterInternal => Ok(None),
+        // NodePorts are opened on every node, the node of the first connector Pod of the applied
+        // StatefulSets is used
+        CurrentlySupportedListenerClasses::ExternalUnstable => {
+            let mut pod_refs = Vec::new();
+            for statefulset in statefulsets {
+                pod_refs.extend(PodRef::from_statefulset(statefulset).context(BuildPodRefsSnafu)?);
+            }
+            let Some(pod_ref) = pod_refs
+                .into_iter()
+                .min_by(|a, b| a.pod_name.cmp(&b.pod_name))
+            else {
+                return Ok(None);
+            };
+            let pod = client
+                .get_opt::<Pod>(&pod_ref.pod_name, &pod_ref.namespace)
+                .await
+                .context(RetrievePodSnafu {
+                    pod: pod_ref.pod_name.clone(),
+                })?;
+
+            Ok(pod
+                .and_then(|pod| pod.status)
+                .and_then(|status| status.host_ip))
+        }
+        CurrentlySupportedListenerClasses::ExternalStable => Ok(role_service
+            .status
+            .as_ref()
+            .and_then(|status| status.load_balancer.as_ref())
+            .and_then(|load_balancer| load_balancer.ingress.as_ref())
+            .and_then(|ingress| ingress.first())
+            .and_then(|ingress| ingress.ip.clone().or_else(|| ingress.hostname.clone()))),
+    }
+}
+
+/// The port under which the web context is reachable from outside of the Kubernetes cluster
+fn external_port(edc: &EDCCluster, role_service: &Service, context: &WebContext) -> Option<i32> {
+    let service_port = role_service
+        .spec
+        .as_ref()?
+        .ports
+        .as_ref()?
+        .iter()
+        .find(|port| port.name.as_deref() == Some(context.port_name))?;
+
+    match edc.spec.cluster_config.listener_class {
+        CurrentlySupportedListenerClasses::ExternalUnstable => service_port.node_port,
+        _ => Some(service_port.port),
+    }
+}
@@ -0,0 +1,324 @@
+//! Kubernetes Events about reconcile failures and rollouts of the EDCClusters
+use std::{collections::HashMap, sync::Mutex};
+
+use stackable_operator::{
+    client::Client,
+    k8s_openapi::api::{apps::v1::StatefulSet, core::v1::ObjectReference},
+    kube::{
+        runtime::{
+            controller,
+            events::{Event, EventType, Recorder, Reporter},
+            reflector::{ObjectRef, Store},
+            watcher,
+        },
+        Resource, ResourceExt,
+    },
+    logging::controller::ReconcilerError,
+};
+
+use crate::crd::EDCCluster;
+
+/// Maximum length of the note of an Event accepted by Kubernetes
+const MAX_NOTE_LENGTH: usize = 1024;
+
+/// Identifies Events which are deduplicated, Events with the same key only differ in their note
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+struct EventKey {
+    object: ObjectRef<EDCCluster>,
+    reason: String,
+    secondary: Option<String>,
+}
+
+/// StatefulSet annotation containing the revision whose rollout was already reported, so that it
+/// is not reported again, e.g. after a restart of the operator
+pub const REPORTED_ROLLOUT_ANNOTATION: &str = "edc.stackable.tech/reported-rollout";
+
+/// Publishes Events for EDCClusters
+///
+/// An Event is not published again if the last Event with the same object, reason and secondary
+/// object has the same note, e.g. a failing reconciliation is only reported when it fails for the
+/// first time or with a different error. The Events of an EDCCluster are forgotten after it was
+/// reconciled successfully or deleted, so that a reoccurring failure is reported again.
+pub struct EventPublisher {
+    client: Client,
+    reporter: Reporter,
+    published: Mutex<HashMap<EventKey, (EventType, String)>>,
+}
+
+impl EventPublisher {
+    pub fn new(client: Client, controller_name: &str) -> Self {
+        Self {
+            client,
+            reporter: Reporter {
+                controller: controller_name.to_string(),
+                instance: None,
+            },
+            published: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Log the result of a reconciliation and publish an Event if it failed
+    ///
+    /// The Events of EDCClusters which are not in the given store anymore are forgotten.
+    pub async fn report_reconciled<ReconcileErr>(
+        &self,
+        result: &Result<
+            (ObjectRef<EDCCluster>, controller::Action),
+            controller::Error<ReconcileErr, watcher::Error>,
+        >,
+        edc_store: &Store<EDCCluster>,
+    ) where
+        ReconcileErr: ReconcilerError + 'static,
+    {
+        self.published
+            .lock()
+            .expect("lock is not poisoned")
+            .retain(|key, _| edc_store.get(&key.object).is_some());
+
+        match result {
+            Ok((object, _)) => {
+                tracing::info!(object = %object, "Reconciled object");
+                self.forget(object);
+            }
+            Err(error) => {
+                tracing::error!(
+                    error = error as &dyn std::error::Error,
+                    "Failed to reconcile object",
+                );
+                if let controller::Error::ReconcilerFailed(error, object) = error {
+                    self.publish(
+                        object,
+                        Event {
+                            type_: EventType::Warning,
+                            reason: error.category().to_string(),
+                            note: Some(error_report(error)),
+                            action: "Reconcile".to_string(),
+                            secondary: error.secondary_object().map(Into::into),
+                        },
+                    )
+                    .await;
+                }
+            }
+        }
+    }
+
+    /// Publish the Event for the given EDCCluster unless it repeats the last one
+    pub async fn publish(&self, object: &ObjectRef<EDCCluster>, mut event: Event) {
+        if let Some(note) = &mut event.note {
+            truncate(note, MAX_NOTE_LENGTH);
+        }
+
+        let key = EventKey {
+            object: object.clone(),
+            reason: event.reason.clone(),
+            secondary: event
+                .secondary
+                .as_ref()
+                .map(|secondary| format!("{secondary:?}")),
+        };
+        let note = event.note.clone().unwrap_or_default();
+        {
+            let mut published = self.published.lock().expect("lock is not poisoned");
+            if published.get(&key).map(|(_, last_note)| last_note) == Some(&note) {
+                tracing::debug!(
+                    object = %object,
+                    reason = %event.reason,
+                    "Suppressed repeated Event"
+                );
+                return;
+            }
+            published.insert(key.clone(), (event.type_, note));
+        }
+
+        let recorder = Recorder::new(
+            self.client.as_kube_client(),
+            self.reporter.clone(),
+            ObjectReference::from(object.clone()),
+        );
+        if let Err(error) = recorder.publish(event).await {
+            tracing::warn!(
+                object = %object,
+                error = &error as &dyn std::error::Error,
+                "Failed to publish Event",
+            );
+            // Allow the Event to be published with the next attempt
+            self.published
+                .lock()
+                .expect("lock is not poisoned")
+                .remove(&key);
+        }
+    }
+
+    /// Publish an Event if the configuration of the rolegroup StatefulSet changed, which rolls
+    /// out its Pods
+    pub async fn report_config_change(
+        &self,
+        object: &ObjectRef<EDCCluster>,
+        statefulset: &StatefulSet,
+        previous_config_hash: Option<&str>,
+        config_hash: &str,
+    ) {
+        // The configuration of a new StatefulSet did not change
+        let Some(previous_config_hash) = previous_config_hash else {
+            return;
+        };
+        if previous_config_hash == config_hash {
+            return;
+        }
+
+        self.publish(
+            object,
+            Event {
+                type_: EventType::Normal,
+                reason: "ConfigurationChanged".to_string(),
+                note: Some(format!(
+                    "The configuration of {statefulset} changed (config hash {previous_config_hash} -> {config_hash}), rolling out its Pods",
+                    statefulset = statefulset.name_any()
+                )),
+                action: "Rollout".to_string(),
+                secondary: Some(statefulset.object_ref(&())),
+            },
+        )
+        .await;
+    }
+
+    /// Publish an Event if all replicas of the current revision of the given rolegroup StatefulSet
+    /// are ready and this rollout was not reported yet
+    ///
+    /// The returned revision must be stored in the [`REPORTED_ROLLOUT_ANNOTATION`] of the
+    /// StatefulSet.
+    pub async fn report_rollout(
+        &self,
+        object: &ObjectRef<EDCCluster>,
+        statefulset: &StatefulSet,
+    ) -> Option<String> {
+        let reported_revision = statefulset
+            .annotations()
+            .get(REPORTED_ROLLOUT_ANNOTATION)
+            .cloned();
+        let Some((revision, replicas)) = rolled_out_revision(statefulset) else {
+            return reported_revision;
+        };
+        if reported_revision.as_deref() == Some(revision) {
+            return reported_revision;
+        }
+
+        self.publish(
+            object,
+            Event {
+                type_: EventType::Normal,
+                reason: "RolloutSucceeded".to_string(),
+                note: Some(format!(
+                    "Rolled out revision {revision} of {statefulset} to {replicas} ready replicas",
+                    statefulset = statefulset.name_any()
+                )),
+                action: "Rollout".to_string(),
+                secondary: Some(statefulset.object_ref(&())),
+            },
+        )
+        .await;
+
+        Some(revision.to_string())
+    }
+
+    fn forget(&self, object: &ObjectRef<EDCCluster>) {
+        self.published
+            .lock()
+            .expect("lock is not poisoned")
+            .retain(|key, _| key.object != *object);
+    }
+}
+
+/// The revision and the number of replicas of the given StatefulSet, if all replicas run the
+/// current revision and are ready
+fn rolled_out_revision(statefulset: &StatefulSet) -> Option<(&str, i32)> {
+    let status = statefulset.status.as_ref()?;
+    let replicas = statefulset
+        .spec
+        .as_ref()
+        .and_then(|spec| spec.replicas)
+        .unwrap_or(1);
+    let rolled_out = replicas > 0
+        && status.observed_generation == statefulset.metadata.generation
+        && status.current_revision == status.update_revision
+        && status.updated_replicas == Some(replicas)
+        && status.ready_replicas == Some(replicas);
+
+    status
+        .update_revision
+        .as_deref()
+        .filter(|_| rolled_out)
+        .map(|revision| (revision, replicas))
+}
+
+/// Render the error including its sources
+fn error_report(error: &dyn std::error::Error) -> String {
+    let mut report = error.to_string();
+    let mut source = error.source();
+    while let Some(error) = source {
+        report.push_str(": ");
+        report.push_str(&error.to_string());
+        source = error.source();
+    }
+    report
+}
+
+/// Truncate the string to at most `max_length` bytes at a character boundary
+fn truncate(string: &mut String, max_length: usize) {
+    if string.len() > max_length {
+        let mut index = max_length;
+        while !string.is_char_boundary(index) {
+            index -= 1;
+        }
+        string.truncate(index);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn statefulset(updated_replicas: i32, ready_replicas: i32) -> StatefulSet {
+        serde_yaml::from_str(&format!(
+            "
+            metadata:
+              name: simple-edc-connector-default
+              generation: 2
+            spec:
+              replicas: 2
+              selector: {{}}
+              serviceName: simple-edc-connector-default
+              template: {{}}
+            status:
+              replicas: 2
+              observedGeneration: 2
+              currentRevision: simple-edc-connector-default-7c9d
+              updateRevision: simple-edc-connector-default-7c9d
+              updatedReplicas: {updated_replicas}
+              readyReplicas: {ready_replicas}
+            "
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_rolled_out_revision() {
+        assert_eq!(
+            Some(("simple-edc-connector-default-7c9d", 2)),
+            rolled_out_revision(&statefulset(2, 2))
+        );
+        assert_eq!(None, rolled_out_revision(&statefulset(2, 1)));
+        assert_eq!(None, rolled_out_revision(&statefulset(1, 2)));
+    }
+
+    #[test]
+    fn test_truncate() {
+        let mut note = "äöü".to_string();
+        truncate(&mut note, 3);
+        assert_eq!("ä", note);
+
+        let mut note = "short".to_string();
+        truncate(&mut note, MAX_NOTE_LENGTH);
+        assert_eq!("short", note);
+    }
+}
@@ -0,0 +1,222 @@
+//! Graceful shutdown of the connectors, which finish their active transfers before they are
+//! stopped, and the `Stopped` condition of a stopped EDCCluster
+use std::collections::BTreeMap;
+
+use snafu::{ResultExt, Snafu};
+use stackable_operator::{
+    builder::pod::{container::ContainerBuilder, PodBuilder},
+    k8s_openapi::api::{
+        apps::v1::StatefulSet,
+        core::v1::{ExecAction, LifecycleHandler},
+    },
+    status::condition::{
+        ClusterCondition, ClusterConditionSet, ClusterConditionStatus, ClusterConditionType,
+        ConditionBuilder,
+    },
+    time::Duration,
+};
+
+use crate::{
+    crd::{ConnectorConfig, CONFIG_PROPERTIES, MANAGEMENT_WEB_CONTEXT, STACKABLE_CONFIG_DIR},
+    endpoints::https_enabled,
+};
+
+/// Time which is left to the connector to shut down after the preStop hook stopped waiting for
+/// the active transfers
+const CONNECTOR_SHUTDOWN_TIME: std::time::Duration = std::time::Duration::from_secs(15);
+/// Interval in which the preStop hook checks for active transfers
+const TRANSFER_POLL_INTERVAL_SECONDS: u64 = 5;
+
+#[derive(Snafu, Debug)]
+pub enum Error {
+    #[snafu(display("failed to set the terminationGracePeriod to {duration}"))]
+    SetTerminationGracePeriod {
+        source: stackable_operator::builder::pod::Error,
+        duration: Duration,
+    },
+}
+
+/// Give the connector the configured `gracefulShutdownTimeout` to finish its active transfers
+///
+/// The preStop hook of the connector container waits until the management API does not report
+/// any started transfer processes anymore, at most until shortly before the grace period ends.
+pub fn add_graceful_shutdown_config(
+    merged_config: &ConnectorConfig,
+    connector_config: Option<&BTreeMap<String, String>>,
+    pod_builder: &mut PodBuilder,
+    container_builder: &mut ContainerBuilder,
+) -> Result<(), Error> {
+    if let Some(graceful_shutdown_timeout) = merged_config.graceful_shutdown_timeout {
+        pod_builder
+            .termination_grace_period(&graceful_shutdown_timeout)
+            .context(SetTerminationGracePeriodSnafu {
+                duration: graceful_shutdown_timeout,
+            })?;
+        container_builder.lifecycle_pre_stop(LifecycleHandler {
+            exec: Some(ExecAction {
+                command: Some(vec![
+                    "/bin/bash".to_string(),
+                    "-c".to_string(),
+                    wait_for_active_transfers_script(
+                        connector_config,
+                        graceful_shutdown_timeout.saturating_sub(CONNECTOR_SHUTDOWN_TIME),
+                    ),
+                ]),
+            }),
+            ..LifecycleHandler::default()
+        });
+    }
+
+    Ok(())
+}
+
+/// Shell script which polls the management API for started transfer processes until there are
+/// none or the given time is over
+///
+/// The API key is read from the mounted config at runtime, so that it is not part of the Pod spec.
+/// If the management API cannot be queried, then there is nothing to wait for.
+fn wait_for_active_transfers_script(
+    connector_config: Option<&BTreeMap<String, String>>,
+    max_wait_time: std::time::Duration,
+) -> String {
+    let (scheme, curl_args) = if https_enabled(connector_config) {
+        ("https", "--insecure ")
+    } else {
+        ("http", "")
+    };
+    let url = format!(
+        "{scheme}://localhost:{port}{path}/v2/transferprocesses/request",
+        port = MANAGEMENT_WEB_CONTEXT.port,
+        path = MANAGEMENT_WEB_CONTEXT.path(connector_config),
+    );
+
+    format!(
+        r#"api_key=$(sed --quiet 's/^edc\.api\.auth\.key=//p' {STACKABLE_CONFIG_DIR}/{CONFIG_PROPERTIES} | sed 's/\\\(.\)/\1/g')
+deadline=$((SECONDS + {max_wait_seconds}))
+while [ "$SECONDS" -lt "$deadline" ]; do
+  transfers=$(curl --silent --fail {curl_args}--header "X-Api-Key: $api_key" --header 'Content-Type: application/json' --data '{{"filterExpression": [{{"operandLeft": "state", "operator": "=", "operandRight": "STARTED"}}]}}' {url}) || exit 0
+  case "$transfers" in
+    *'"@id"'*) echo "Waiting for active transfers to finish"; sleep {TRANSFER_POLL_INTERVAL_SECONDS} ;;
+    *) exit 0 ;;
+  esac
+done"#,
+        max_wait_seconds = max_wait_time.as_secs(),
+    )
+}
+
+/// Reports whether the connector Pods of a stopped EDCCluster are terminated
+///
+/// The condition refines the `Stopped` condition of the `ClusterOperationsConditionBuilder`,
+/// which only reflects `clusterOperation.stopped`, and must therefore be applied after it.
+#[derive(Debug)]
+pub struct StoppedConditionBuilder {
+    stopped: bool,
+    running_pods: i32,
+}
+
+impl StoppedConditionBuilder {
+    pub fn new(stopped: bool) -> Self {
+        Self {
+            stopped,
+            running_pods: 0,
+        }
+    }
+
+    /// Count the Pods of the given StatefulSet, which are still running
+    pub fn add(&mut self, statefulset: &StatefulSet) {
+        self.running_pods += statefulset
+            .status
+            .as_ref()
+            .map(|status| status.replicas)
+            .unwrap_or_default();
+    }
+
+    fn stopped(&self) -> Option<ClusterCondition> {
+        if !self.stopped {
+            return None;
+        }
+
+        let (status, reason, message) = if self.running_pods == 0 {
+            (
+                ClusterConditionStatus::True,
+                "Stopped",
+                "All connector Pods are terminated".to_string(),
+            )
+        } else {
+            (
+                ClusterConditionStatus::False,
+                "Stopping",
+                format!(
+                    "Waiting for {} connector Pods to finish their transfers and terminate",
+                    self.running_pods
+                ),
+            )
+        };
+
+        Some(ClusterCondition {
+            reason: Some(reason.to_string()),
+            message: Some(message),
+            status,
+            type_: ClusterConditionType::Stopped,
+            last_transition_time: None,
+            last_update_time: None,
+        })
+    }
+}
+
+impl ConditionBuilder for StoppedConditionBuilder {
+    fn build_conditions(&self) -> ClusterConditionSet {
+        self.stopped().into_iter().collect::<Vec<_>>().into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn statefulset(replicas: i32) -> StatefulSet {
+        serde_yaml::from_str(&format!(
+            r#"
+            apiVersion: apps/v1
+            kind: StatefulSet
+            metadata:
+              name: provider-server-default
+            status:
+              replicas: {replicas}
+            "#
+        ))
+        .expect("invalid test input")
+    }
+
+    #[test]
+    fn test_stopped_condition() {
+        let mut builder = StoppedConditionBuilder::new(false);
+        builder.add(&statefulset(1));
+        assert_eq!(None, builder.stopped());
+
+        let mut builder = StoppedConditionBuilder::new(true);
+        builder.add(&statefulset(0));
+        builder.add(&statefulset(2));
+        let condition = builder.stopped().unwrap();
+        assert_eq!(ClusterConditionStatus::False, condition.status);
+        assert_eq!(Some("Stopping".to_string()), condition.reason);
+
+        let mut builder = StoppedConditionBuilder::new(true);
+        builder.add(&statefulset(0));
+        let condition = builder.stopped().unwrap();
+        assert_eq!(ClusterConditionStatus::True, condition.status);
+        assert_eq!(Some("Stopped".to_string()), condition.reason);
+    }
+
+    #[test]
+    fn test_wait_for_active_transfers_script() {
+        let script = wait_for_active_transfers_script(
+            Some(&[("web.http.management.path".to_string(), "/mgmt/".to_string())].into()),
+            std::time::Duration::from_secs(285),
+        );
+
+        assert!(script.contains("deadline=$((SECONDS + 285))"));
+        assert!(script.contains(" http://localhost:8182/mgmt/v2/transferprocesses/request)"));
+        assert!(script.contains("/stackable/config/config.properties"));
+    }
+}
@@ -0,0 +1,549 @@
+//! Health of the connectors as reported by the health check of the EDC observability API
+//!
+//! The health is checked in every reconciliation, see [`check_health`], which is requeued every
+//! [`HEALTH_CHECK_INTERVAL`], so that the `Degraded` condition is written together with the other
+//! conditions and stays up to date.
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::Arc,
+    time::Duration,
+};
+
+use futures::future::join_all;
+use http_body_util::{BodyExt, Empty, Limited};
+use hyper::{
+    body::{Bytes, Incoming},
+    header::{ACCEPT, HOST},
+    Request, Response, StatusCode,
+};
+use hyper_util::rt::TokioIo;
+use product_config::types::PropertyNameKind;
+use serde::Deserialize;
+use snafu::{OptionExt, ResultExt, Snafu};
+use stackable_operator::{
+    k8s_openapi::api::apps::v1::StatefulSet,
+    kube::ResourceExt,
+    status::condition::{
+        ClusterCondition, ClusterConditionSet, ClusterConditionStatus, ClusterConditionType,
+        ConditionBuilder,
+    },
+};
+use tokio::{
+    io::{AsyncRead, AsyncWrite},
+    net::TcpStream,
+};
+use tokio_rustls::{
+    rustls::{
+        self,
+        client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier},
+        crypto::{ring, verify_tls12_signature, verify_tls13_signature, CryptoProvider},
+        pki_types::{CertificateDer, InvalidDnsNameError, ServerName, UnixTime},
+        ClientConfig, DigitallySignedStruct, SignatureScheme,
+    },
+    TlsConnector,
+};
+
+use crate::{
+    crd::{EDCCluster, PodRef, CONFIG_PROPERTIES, DEFAULT_WEB_CONTEXT},
+    endpoints::https_enabled,
+};
+
+/// Interval in which the reconciliation is requeued to refresh the health of the connectors
+pub const HEALTH_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+/// Time a connector has to answer the health check
+const HEALTH_CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+/// Maximum size of the health status, it only lists the components of the connector
+const MAX_HEALTH_STATUS_SIZE: usize = 64 * 1024;
+
+#[derive(Snafu, Debug)]
+pub enum Error {
+    #[snafu(display("failed to configure the TLS client"))]
+    ConfigureTls { source: rustls::Error },
+    #[snafu(display("failed to build the Pod references"))]
+    BuildPodRefs {
+        source: crate::crd::NoNamespaceError,
+    },
+    #[snafu(display("failed to connect to [{address}]"))]
+    Connect {
+        source: std::io::Error,
+        address: String,
+    },
+    #[snafu(display("[{host}] is not a valid server name"))]
+    InvalidServerName {
+        source: InvalidDnsNameError,
+        host: String,
+    },
+    #[snafu(display("failed to establish a TLS connection to [{address}]"))]
+    TlsHandshake {
+        source: std::io::Error,
+        address: String,
+    },
+    #[snafu(display("failed to build the health check request"))]
+    BuildRequest { source: hyper::http::Error },
+    #[snafu(display("failed to send the health check request"))]
+    SendRequest { source: hyper::Error },
+    #[snafu(display("the health check responded with the unexpected status {status}"))]
+    UnexpectedStatus { status: StatusCode },
+    #[snafu(display(
+        "failed to read the health status, which must not exceed {MAX_HEALTH_STATUS_SIZE} bytes"
+    ))]
+    ReadHealthStatus {
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
+    #[snafu(display("the health check did not finish within {HEALTH_CHECK_TIMEOUT:?}"))]
+    Timeout,
+    #[snafu(display("failed to parse the health status"))]
+    ParseHealthStatus { source: serde_json::Error },
+}
+
+type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// Health status as returned by the `/check/health` endpoint of the observability API
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct HealthStatus {
+    #[serde(default)]
+    component_results: Vec<ComponentResult>,
+    is_system_healthy: bool,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ComponentResult {
+    component: Option<String>,
+    is_healthy: bool,
+    failure: Option<Failure>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Failure {
+    #[serde(default)]
+    messages: Vec<String>,
+}
+
+#[derive(Debug, PartialEq)]
+enum PodHealth {
+    Healthy,
+    /// The failing components including their failure messages
+    Unhealthy(Vec<String>),
+    /// The reason why the health check failed
+    Unknown(String),
+}
+
+impl From<HealthStatus> for PodHealth {
+    fn from(status: HealthStatus) -> Self {
+        if status.is_system_healthy {
+            return PodHealth::Healthy;
+        }
+
+        PodHealth::Unhealthy(
+            status
+                .component_results
+                .into_iter()
+                .filter(|result| !result.is_healthy)
+                .map(|result| {
+                    let component = result
+                        .component
+                        .unwrap_or_else(|| "unknown component".to_string());
+                    match result.failure {
+                        Some(Failure { messages }) if !messages.is_empty() => {
+                            format!("{component} ({})", messages.join(", "))
+                        }
+                        _ => component,
+                    }
+                })
+                .collect(),
+        )
+    }
+}
+
+/// Where a connector serves the observability API
+#[derive(Clone, Debug)]
+struct HealthEndpoint {
+    https: bool,
+    web_http_path: String,
+}
+
+/// Reports the `Degraded` condition of the connectors which were checked by [`check_health`]
+#[derive(Debug)]
+pub struct ConnectorHealthConditionBuilder {
+    degraded: ClusterCondition,
+}
+
+impl ConditionBuilder for ConnectorHealthConditionBuilder {
+    fn build_conditions(&self) -> ClusterConditionSet {
+        vec![self.degraded.clone()].into()
+    }
+}
+
+/// Check the health of the Pods of the given rolegroup StatefulSets
+///
+/// The Pods are checked concurrently, each one has [`HEALTH_CHECK_TIMEOUT`] to answer. Stopped
+/// connectors are not checked.
+pub async fn check_health(
+    tls_connector: &TlsConnector,
+    edc: &EDCCluster,
+    server_config: &HashMap<String, HashMap<PropertyNameKind, BTreeMap<String, String>>>,
+    statefulsets: &[StatefulSet],
+) -> Result<ConnectorHealthConditionBuilder> {
+    let mut pods = Vec::new();
+    if !edc.spec.cluster_operation.stopped {
+        for (rolegroup_name, rolegroup_config) in server_config {
+            let connector_config =
+                rolegroup_config.get(&PropertyNameKind::File(CONFIG_PROPERTIES.to_string()));
+            let endpoint = HealthEndpoint {
+                https: https_enabled(connector_config),
+                web_http_path: DEFAULT_WEB_CONTEXT.path(connector_config).to_string(),
+            };
+            let statefulset_name = edc.server_rolegroup_ref(rolegroup_name).object_name();
+            for statefulset in statefulsets
+                .iter()
+                .filter(|statefulset| statefulset.name_any() == statefulset_name)
+            {
+                for pod in PodRef::from_statefulset(statefulset).context(BuildPodRefsSnafu)? {
+                    pods.push((pod, endpoint.clone()));
+                }
+            }
+        }
+    }
+
+    let health = join_all(pods.into_iter().map(|(pod, endpoint)| async move {
+        let health = match query_health(tls_connector, &pod, &endpoint).await {
+            Ok(status) => PodHealth::from(status),
+            Err(error) => PodHealth::Unknown(snafu::Report::from_error(error).to_string()),
+        };
+        (pod.pod_name, health)
+    }))
+    .await
+    .into_iter()
+    .collect::<BTreeMap<_, _>>();
+
+    Ok(ConnectorHealthConditionBuilder {
+        degraded: degraded_condition(&health),
+    })
+}
+
+/// The `Degraded` condition of the connectors with the given health
+///
+/// Without Pods, e.g. if the connectors are stopped, nothing is degraded, so that a previously
+/// reported degradation is cleared.
+fn degraded_condition(pods: &BTreeMap<String, PodHealth>) -> ClusterCondition {
+    let unhealthy = pods
+        .iter()
+        .filter_map(|(pod, health)| match health {
+            PodHealth::Unhealthy(components) => Some(format!("{pod}: {}", components.join("; "))),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    let unknown = pods
+        .iter()
+        .filter_map(|(pod, health)| match health {
+            PodHealth::Unknown(reason) => Some(format!("{pod}: {reason}")),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+
+    let (status, message) = if pods.is_empty() {
+        (
+            ClusterConditionStatus::False,
+            "No connectors are running".to_string(),
+        )
+    } else if !unhealthy.is_empty() {
+        (
+            ClusterConditionStatus::True,
+            format!(
+                "Connectors report failing components: {}",
+                unhealthy.join(", ")
+            ),
+        )
+    } else if !unknown.is_empty() {
+        (
+            ClusterConditionStatus::Unknown,
+            format!(
+                "The health of the connectors could not be checked: {}",
+                unknown.join(", ")
+            ),
+        )
+    } else {
+        (
+            ClusterConditionStatus::False,
+            "All connectors report healthy components".to_string(),
+        )
+    };
+
+    ClusterCondition {
+        reason: None,
+        message: Some(message),
+        status,
+        type_: ClusterConditionType::Degraded,
+        last_transition_time: None,
+        last_update_time: None,
+    }
+}
+
+async fn query_health(
+    tls_connector: &TlsConnector,
+    pod: &PodRef,
+    endpoint: &HealthEndpoint,
+) -> Result<HealthStatus> {
+    tokio::time::timeout(
+        HEALTH_CHECK_TIMEOUT,
+        request_health(tls_connector, &pod.fqdn(), endpoint),
+    )
+    .await
+    .ok()
+    .context(TimeoutSnafu)?
+}
+
+async fn request_health(
+    tls_connector: &TlsConnector,
+    host: &str,
+    endpoint: &HealthEndpoint,
+) -> Result<HealthStatus> {
+    let address = format!("{host}:{}", DEFAULT_WEB_CONTEXT.port);
+    let stream = TcpStream::connect(&address).await.context(ConnectSnafu {
+        address: address.clone(),
+    })?;
+
+    let request = Request::get(format!("{}/check/health", endpoint.web_http_path))
+        .header(HOST, host)
+        .header(ACCEPT, "application/json")
+        .body(Empty::<Bytes>::new())
+        .context(BuildRequestSnafu)?;
+
+    let response = if endpoint.https {
+        let server_name =
+            ServerName::try_from(host.to_string()).context(InvalidServerNameSnafu { host })?;
+        let stream = tls_connector
+            .connect(server_name, stream)
+            .await
+            .context(TlsHandshakeSnafu { address })?;
+        send_request(stream, request).await?
+    } else {
+        send_request(stream, request).await?
+    };
+
+    let status = response.status();
+    let body = Limited::new(response.into_body(), MAX_HEALTH_STATUS_SIZE)
+        .collect()
+        .await
+        .context(ReadHealthStatusSnafu)?
+        .to_bytes();
+
+    parse_health_status(status, &body)
+}
+
+async fn send_request<S>(stream: S, request: Request<Empty<Bytes>>) -> Result<Response<Incoming>>
+where
+    S: AsyncRead + AsyncWrite + Send + Unpin + 'static,
+{
+    let (mut sender, connection) = hyper::client::conn::http1::handshake(TokioIo::new(stream))
+        .await
+        .context(SendRequestSnafu)?;
+    // The connection is closed once the response is read and the sender is dropped
+    tokio::spawn(async move {
+        if let Err(error) = connection.await {
+            tracing::debug!(
+                error = &error as &dyn std::error::Error,
+                "Failed to complete the health check connection",
+            );
+        }
+    });
+
+    sender.send_request(request).await.context(SendRequestSnafu)
+}
+
+/// Parse the health status from the response body, the connector answers with `200 OK` if it is
+/// healthy and with `503 Service Unavailable` otherwise, in both cases with the health status as
+/// body.
+fn parse_health_status(status: StatusCode, body: &[u8]) -> Result<HealthStatus> {
+    if status != StatusCode::OK && status != StatusCode::SERVICE_UNAVAILABLE {
+        return UnexpectedStatusSnafu { status }.fail();
+    }
+
+    serde_json::from_slice(body).context(ParseHealthStatusSnafu)
+}
+
+/// TLS client which accepts any server certificate
+///
+/// The connectors usually serve certificates which the operator cannot verify, e.g. self-signed
+/// ones. The kubelet does not verify the certificates in HTTPS probes either.
+pub fn tls_connector() -> Result<TlsConnector> {
+    let provider = Arc::new(ring::default_provider());
+    let config = ClientConfig::builder_with_provider(provider.clone())
+        .with_safe_default_protocol_versions()
+        .context(ConfigureTlsSnafu)?
+        .dangerous()
+        .with_custom_certificate_verifier(Arc::new(NoServerCertVerification(provider)))
+        .with_no_client_auth();
+
+    Ok(TlsConnector::from(Arc::new(config)))
+}
+
+/// Accepts any server certificate but still verifies the handshake signatures
+#[derive(Debug)]
+struct NoServerCertVerification(Arc<CryptoProvider>);
+
+impl ServerCertVerifier for NoServerCertVerification {
+    fn verify_server_cert(
+        &self,
+        _end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        Ok(ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls12_signature(
+            message,
+            cert,
+            dss,
+            &self.0.signature_verification_algorithms,
+        )
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls13_signature(
+            message,
+            cert,
+            dss,
+            &self.0.signature_verification_algorithms,
+        )
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.0.signature_verification_algorithms.supported_schemes()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fixtures::edc_cluster;
+
+    const UNHEALTHY_STATUS: &str = r#"{
+        "componentResults": [
+            {"component": "BaseRuntime", "isHealthy": true, "failure": null},
+            {
+                "component": "Vault",
+                "isHealthy": false,
+                "failure": {"messages": ["token expired"], "failureDetail": "token expired"}
+            }
+        ],
+        "isSystemHealthy": false
+    }"#;
+
+    #[test]
+    fn test_parse_unhealthy_status() {
+        let health = PodHealth::from(
+            parse_health_status(StatusCode::SERVICE_UNAVAILABLE, UNHEALTHY_STATUS.as_bytes())
+                .unwrap(),
+        );
+
+        assert_eq!(
+            PodHealth::Unhealthy(vec!["Vault (token expired)".to_string()]),
+            health
+        );
+    }
+
+    #[test]
+    fn test_parse_unexpected_status() {
+        assert!(matches!(
+            parse_health_status(StatusCode::NOT_FOUND, b"Not Found"),
+            Err(Error::UnexpectedStatus {
+                status: StatusCode::NOT_FOUND
+            })
+        ));
+        assert!(matches!(
+            parse_health_status(StatusCode::OK, b"not json"),
+            Err(Error::ParseHealthStatus { .. })
+        ));
+    }
+
+    #[test]
+    fn test_degraded_condition() {
+        let mut pods = BTreeMap::new();
+        let degraded = degraded_condition(&pods);
+        assert_eq!(ClusterConditionStatus::False, degraded.status);
+        assert_eq!(
+            Some("No connectors are running".to_string()),
+            degraded.message
+        );
+
+        pods.insert("connector-default-0".to_string(), PodHealth::Healthy);
+        assert_eq!(
+            ClusterConditionStatus::False,
+            degraded_condition(&pods).status
+        );
+
+        pods.insert(
+            "connector-default-1".to_string(),
+            PodHealth::Unknown("connection refused".to_string()),
+        );
+        assert_eq!(
+            ClusterConditionStatus::Unknown,
+            degraded_condition(&pods).status
+        );
+
+        pods.insert(
+            "connector-default-2".to_string(),
+            PodHealth::Unhealthy(vec!["S3".to_string()]),
+        );
+        let degraded = degraded_condition(&pods);
+        assert_eq!(ClusterConditionStatus::True, degraded.status);
+        assert_eq!(
+            Some("Connectors report failing components: connector-default-2: S3".to_string()),
+            degraded.message
+        );
+    }
+
+    #[tokio::test]
+    async fn test_check_health_without_pods() {
+        let edc = edc_cluster("roleGroups: {default: {replicas: 1}}");
+        let server_config = HashMap::from([("default".to_string(), HashMap::new())]);
+        // The StatefulSet has not created any Pods yet
+        let statefulset = serde_yaml::from_str::<StatefulSet>(
+            "
+            metadata:
+              name: provider-server-default
+              namespace: default
+            status:
+              replicas: 0
+            ",
+        )
+        .unwrap();
+
+        let conditions = Vec::<ClusterCondition>::from(
+            check_health(
+                &tls_connector().unwrap(),
+                &edc,
+                &server_config,
+                &[statefulset],
+            )
+            .await
+            .unwrap()
+            .build_conditions(),
+        );
+
+        assert_eq!(1, conditions.len());
+        assert_eq!(ClusterConditionType::Degraded, conditions[0].type_);
+        assert_eq!(ClusterConditionStatus::False, conditions[0].status);
+        assert_eq!(
+            Some("No connectors are running".to_string()),
+            conditions[0].message
+        );
+    }
+}
@@ -1,25 +1,57 @@
 mod affinity;
+mod autoscaling;
 mod controller;
 mod crd;
+mod discovery;
+mod endpoints;
+mod events;
+mod graceful_shutdown;
+mod health;
+mod metrics;
+mod network_policy;
+mod pdb;
 mod product_logging;
+mod render;
+mod security_context;
+#[cfg(test)]
+mod test_fixtures;
+mod validation;
+mod webhook;
 
-use std::sync::Arc;
+use std::{
+    net::{Ipv4Addr, SocketAddr},
+    path::PathBuf,
+    sync::{Arc, Mutex},
+    time::Instant,
+};
 
 use clap::{crate_description, crate_version, Parser};
-use crd::{EDCCluster, APP_NAME};
+use crd::{EDCCluster, EDCClusterVersions, APP_NAME};
 use futures::stream::StreamExt;
 use stackable_operator::{
     cli::{Command, ProductOperatorRun},
+    commons::s3::{S3Bucket, S3BucketDef, S3Connection, S3ConnectionDef},
     k8s_openapi::api::{
         apps::v1::StatefulSet,
-        core::v1::{ConfigMap, Service},
+        autoscaling::v2::HorizontalPodAutoscaler,
+        core::v1::{ConfigMap, Secret, Service},
+        networking::v1::NetworkPolicy,
+        policy::v1::PodDisruptionBudget,
+    },
+    kube::{
+        core::PartialObjectMeta,
+        runtime::{
+            metadata_watcher,
+            reflector::{self, ObjectRef, Store},
+            watcher, Controller, WatchStreamExt,
+        },
+        Resource, ResourceExt,
     },
-    kube::runtime::{watcher, Controller},
-    logging::controller::report_controller_reconciled,
+    logging::controller::ReconcilerError,
     CustomResourceExt,
 };
 
-use crate::controller::EDC_CONTROLLER_NAME;
+use crate::{controller::EDC_CONTROLLER_NAME, events::EventPublisher, metrics::Metrics};
 
 mod built_info {
     include!(concat!(env!("OUT_DIR"), "/built.rs"));
@@ -31,19 +63,80 @@ const OPERATOR_NAME: &str = "edc.stackable.tech";
 #[clap(about, author)]
 struct Opts {
     #[clap(subcommand)]
-    cmd: Command,
+    cmd: Cmd,
+}
+
+#[derive(clap::Subcommand)]
+enum Cmd {
+    #[command(flatten)]
+    Operator(Command<OperatorRun>),
+
+    /// Print the resources which the operator would create for the given EDCCluster without
+    /// connecting to a Kubernetes cluster
+    Render(render::RenderArgs),
+}
+
+/// Arguments of the `run` command
+#[derive(clap::Args)]
+struct OperatorRun {
+    #[command(flatten)]
+    common: ProductOperatorRun,
+
+    /// Port of the HTTP server which exposes the metrics of the operator under `/metrics`
+    #[arg(long, env = "METRICS_PORT", default_value_t = 8080)]
+    metrics_port: u16,
+
+    /// Namespace of the operator, which is allowed to call the connectors in the NetworkPolicies
+    #[arg(long, env = "OPERATOR_NAMESPACE")]
+    operator_namespace: Option<String>,
+
+    /// Port of the HTTPS server which serves the admission and conversion webhooks
+    #[arg(long, env = "WEBHOOK_PORT", default_value_t = 8443)]
+    webhook_port: u16,
+
+    /// Directory with the certificate (`tls.crt`), the private key (`tls.key`) and the CA
+    /// certificate (`ca.crt`) of the webhooks, the webhooks are only served if it is set
+    #[arg(long, env = "WEBHOOK_TLS_DIR")]
+    webhook_tls_dir: Option<PathBuf>,
+
+    /// Name of the Service in front of the webhook, the Kubernetes API server converts
+    /// EDCClusters between their versions with the webhook if it is set together with the
+    /// namespace of the Service
+    #[arg(long, env = "WEBHOOK_SERVICE_NAME")]
+    webhook_service_name: Option<String>,
+
+    /// Namespace of the Service in front of the webhook
+    #[arg(long, env = "WEBHOOK_SERVICE_NAMESPACE")]
+    webhook_service_namespace: Option<String>,
 }
 
+/// Locations of the product config which are searched if no path is given
+const PRODUCT_CONFIG_PATHS: [&str; 2] = [
+    "deploy/config-spec/properties.yaml",
+    "/etc/stackable/edc-operator/config-spec/properties.yaml",
+];
+
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     let opts = Opts::parse();
     match opts.cmd {
-        Command::Crd => EDCCluster::print_yaml_schema(built_info::PKG_VERSION)?,
-        Command::Run(ProductOperatorRun {
-            product_config,
-            watch_namespace,
-            tracing_target,
-        }) => {
+        Cmd::Operator(Command::Crd) => {
+            EDCClusterVersions::print_yaml_schema(built_info::PKG_VERSION)?
+        }
+        Cmd::Operator(Command::Run(OperatorRun {
+            common:
+                ProductOperatorRun {
+                    product_config,
+                    watch_namespace,
+                    tracing_target,
+                },
+            metrics_port,
+            operator_namespace,
+            webhook_port,
+            webhook_tls_dir,
+            webhook_service_name,
+            webhook_service_namespace,
+        })) => {
             stackable_operator::logging::initialize_logging(
                 "EDC_OPERATOR_LOG",
                 APP_NAME,
@@ -58,50 +151,292 @@ async fn main() -> anyhow::Result<()> {
                 built_info::RUSTC_VERSION,
             );
 
-            let product_config = product_config.load(&[
-                "deploy/config-spec/properties.yaml",
-                "/etc/stackable/edc-operator/config-spec/properties.yaml",
-            ])?;
+            let product_config = product_config.load(&PRODUCT_CONFIG_PATHS)?;
 
             let client =
                 stackable_operator::client::create_client(Some(OPERATOR_NAME.to_string())).await?;
 
-            Controller::new(
+            // The StatefulSets are cached, so that the reconciliation can compare the applied
+            // StatefulSets with their previous state without retrieving them
+            let (statefulset_store, statefulset_writer) = reflector::store();
+
+            let ctx = Arc::new(controller::Ctx {
+                client: client.clone(),
+                product_config,
+                event_publisher: EventPublisher::new(
+                    client.clone(),
+                    &format!("{EDC_CONTROLLER_NAME}.{OPERATOR_NAME}"),
+                ),
+                statefulsets: statefulset_store,
+                operator_namespace,
+                reconcile_failures: Mutex::default(),
+                tls_connector: health::tls_connector()?,
+            });
+
+            let webhook_server = if let Some(tls_dir) = webhook_tls_dir {
+                // The operator fails if the webhook server cannot be started, and the conversion
+                // is only enabled once the server listens for requests
+                let server = webhook::bind(
+                    SocketAddr::from((Ipv4Addr::UNSPECIFIED, webhook_port)),
+                    &tls_dir,
+                )
+                .await?;
+                if let (Some(service_name), Some(service_namespace)) =
+                    (&webhook_service_name, &webhook_service_namespace)
+                {
+                    webhook::enable_crd_conversion(
+                        &client,
+                        service_name,
+                        service_namespace,
+                        &tls_dir,
+                    )
+                    .await?;
+                }
+
+                Some(tokio::spawn(server.serve()))
+            } else {
+                None
+            };
+
+            let edc_controller = Controller::new(
                 watch_namespace.get_api::<EDCCluster>(&client),
                 watcher::Config::default(),
-            )
-            .owns(
-                watch_namespace.get_api::<Service>(&client),
-                watcher::Config::default(),
-            )
-            .owns(
-                watch_namespace.get_api::<StatefulSet>(&client),
-                watcher::Config::default(),
-            )
-            .owns(
-                watch_namespace.get_api::<ConfigMap>(&client),
-                watcher::Config::default(),
-            )
-            .shutdown_on_signal()
-            .run(
-                controller::reconcile_edc,
-                controller::error_policy,
-                Arc::new(controller::Ctx {
-                    client: client.clone(),
-                    product_config,
-                }),
-            )
-            .map(|res| {
-                report_controller_reconciled(
-                    &client,
-                    &format!("{EDC_CONTROLLER_NAME}.{OPERATOR_NAME}"),
-                    &res,
-                );
-            })
-            .collect::<()>()
-            .await;
+            );
+            let edc_store_1 = edc_controller.store();
+            let edc_store_2 = edc_controller.store();
+            let edc_store_3 = edc_controller.store();
+            let edc_store_4 = edc_controller.store();
+            let edc_store_5 = edc_controller.store();
+
+            // The S3Buckets are cached, so that a changed S3Connection only triggers the
+            // EDCClusters whose referenced S3Bucket uses it
+            let (s3_bucket_store, s3_bucket_writer) = reflector::store();
+
+            let metrics = Arc::new(Metrics::new()?);
+            let metrics_server = tokio::spawn({
+                let metrics = metrics.clone();
+                let edc_store = edc_controller.store();
+                async move {
+                    let address = SocketAddr::from((Ipv4Addr::UNSPECIFIED, metrics_port));
+                    if let Err(error) = metrics::serve(address, metrics, edc_store).await {
+                        tracing::error!(
+                            error = &error as &dyn std::error::Error,
+                            "Failed to serve the operator metrics",
+                        );
+                    }
+                }
+            });
+
+            edc_controller
+                .owns(
+                    watch_namespace.get_api::<Service>(&client),
+                    watcher::Config::default(),
+                )
+                .owns_stream(
+                    watcher(
+                        watch_namespace.get_api::<StatefulSet>(&client),
+                        watcher::Config::default(),
+                    )
+                    .default_backoff()
+                    .reflect(statefulset_writer)
+                    .touched_objects(),
+                )
+                .owns(
+                    watch_namespace.get_api::<PodDisruptionBudget>(&client),
+                    watcher::Config::default(),
+                )
+                .owns(
+                    watch_namespace.get_api::<NetworkPolicy>(&client),
+                    watcher::Config::default(),
+                )
+                .owns(
+                    watch_namespace.get_api::<HorizontalPodAutoscaler>(&client),
+                    watcher::Config::default(),
+                )
+                .watches_stream(
+                    watcher(
+                        watch_namespace.get_api::<S3Bucket>(&client),
+                        watcher::Config::default(),
+                    )
+                    .default_backoff()
+                    .reflect(s3_bucket_writer)
+                    .touched_objects(),
+                    move |bucket| {
+                        edc_store_1
+                            .state()
+                            .into_iter()
+                            .filter(move |edc| references_s3_bucket(edc, &bucket))
+                            .map(|edc| ObjectRef::from_obj(&*edc))
+                    },
+                )
+                .watches(
+                    watch_namespace.get_api::<S3Connection>(&client),
+                    watcher::Config::default(),
+                    move |connection| {
+                        let s3_bucket_store = s3_bucket_store.clone();
+                        edc_store_2
+                            .state()
+                            .into_iter()
+                            .filter(move |edc| {
+                                references_s3_connection(edc, &connection, &s3_bucket_store)
+                            })
+                            .map(|edc| ObjectRef::from_obj(&*edc))
+                    },
+                )
+                // The owned ConfigMaps and the referenced Vector aggregator ConfigMap share one
+                // watch instead of watching all ConfigMaps twice
+                .watches(
+                    watch_namespace.get_api::<ConfigMap>(&client),
+                    watcher::Config::default(),
+                    move |config_map| {
+                        let owner = owning_edc_cluster(&config_map);
+                        edc_store_3
+                            .state()
+                            .into_iter()
+                            .filter(move |edc| references_config_map(edc, &config_map))
+                            .map(|edc| ObjectRef::from_obj(&*edc))
+                            .chain(owner)
+                    },
+                )
+                // Only the metadata of the Secrets is watched, their data is not needed to find
+                // the referencing EDCClusters
+                .watches_stream(
+                    metadata_watcher(
+                        watch_namespace.get_api::<Secret>(&client),
+                        watcher::Config::default(),
+                    )
+                    .default_backoff()
+                    .touched_objects(),
+                    move |secret| {
+                        edc_store_4
+                            .state()
+                            .into_iter()
+                            .filter(move |edc| references_secret(edc, &secret))
+                            .map(|edc| ObjectRef::from_obj(&*edc))
+                    },
+                )
+                .shutdown_on_signal()
+                .run(
+                    move |edc, ctx| {
+                        let metrics = metrics.clone();
+                        async move {
+                            let start = Instant::now();
+                            let result = controller::reconcile_edc(edc, ctx).await;
+                            metrics.record_reconcile(
+                                start.elapsed(),
+                                result.as_ref().err().map(ReconcilerError::category),
+                            );
+                            result
+                        }
+                    },
+                    controller::error_policy,
+                    ctx.clone(),
+                )
+                .for_each(|res| {
+                    let ctx = ctx.clone();
+                    let edc_store = edc_store_5.clone();
+                    async move {
+                        // Deleted EDCClusters are not reconciled again, so their failures are
+                        // forgotten here
+                        ctx.reconcile_failures
+                            .lock()
+                            .expect("lock is not poisoned")
+                            .retain(|edc, _| edc_store.get(edc).is_some());
+                        ctx.event_publisher
+                            .report_reconciled(&res, &edc_store)
+                            .await
+                    }
+                })
+                .await;
+
+            metrics_server.abort();
+            if let Some(webhook_server) = webhook_server {
+                webhook_server.abort();
+            }
+        }
+        Cmd::Render(args) => {
+            let product_config = args.product_config.load(&PRODUCT_CONFIG_PATHS)?;
+            print!("{}", render::render(&args, &product_config)?);
         }
     }
 
     Ok(())
 }
+
+/// Objects can only be referenced by EDCClusters in the same namespace
+fn same_namespace(edc: &EDCCluster, other: &impl Resource) -> bool {
+    edc.metadata.namespace == other.meta().namespace
+}
+
+fn references_s3_bucket(edc: &EDCCluster, bucket: &S3Bucket) -> bool {
+    if !same_namespace(edc, bucket) {
+        return false;
+    }
+
+    match &edc.spec.cluster_config.ionos.s3 {
+        S3BucketDef::Reference(bucket_name) => bucket.name_any() == *bucket_name,
+        S3BucketDef::Inline(_) => false,
+    }
+}
+
+fn references_s3_connection(
+    edc: &EDCCluster,
+    connection: &S3Connection,
+    s3_buckets: &Store<S3Bucket>,
+) -> bool {
+    if !same_namespace(edc, connection) {
+        return false;
+    }
+
+    let bucket_connection = match &edc.spec.cluster_config.ionos.s3 {
+        S3BucketDef::Inline(bucket) => bucket.connection.clone(),
+        S3BucketDef::Reference(bucket_name) => {
+            let bucket_ref =
+                ObjectRef::new(bucket_name).within(&edc.namespace().unwrap_or_default());
+            s3_buckets
+                .get(&bucket_ref)
+                .and_then(|bucket| bucket.spec.connection.clone())
+        }
+    };
+
+    matches!(
+        bucket_connection,
+        Some(S3ConnectionDef::Reference(connection_name))
+            if connection.name_any() == connection_name
+    )
+}
+
+/// The EDCCluster which owns the given ConfigMap, e.g. a rolegroup or discovery ConfigMap
+fn owning_edc_cluster(config_map: &ConfigMap) -> Option<ObjectRef<EDCCluster>> {
+    let namespace = config_map.namespace()?;
+    config_map
+        .owner_references()
+        .iter()
+        .find(|owner| {
+            owner.kind == EDCCluster::kind(&())
+                && owner
+                    .api_version
+                    .starts_with(&format!("{}/", EDCCluster::group(&())))
+        })
+        .map(|owner| ObjectRef::new(&owner.name).within(&namespace))
+}
+
+fn references_config_map(edc: &EDCCluster, config_map: &ConfigMap) -> bool {
+    same_namespace(edc, config_map)
+        && edc
+            .spec
+            .cluster_config
+            .vector_aggregator_config_map_name
+            .as_deref()
+            == Some(config_map.name_any().as_str())
+}
+
+fn references_secret(edc: &EDCCluster, secret: &PartialObjectMeta<Secret>) -> bool {
+    if !same_namespace(edc, secret) {
+        return false;
+    }
+
+    let secret_name = secret.name_any();
+    edc.spec.cluster_config.ionos.token_secret == secret_name
+        || edc.spec.cluster_config.cert_secret == secret_name
+}
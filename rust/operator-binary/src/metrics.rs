@@ -0,0 +1,258 @@
+//! Prometheus metrics about the operator itself, served under `/metrics`
+use std::{convert::Infallible, net::SocketAddr, sync::Arc, time::Duration};
+
+use http_body_util::Full;
+use hyper::{
+    body::{Bytes, Incoming},
+    header::CONTENT_TYPE,
+    server::conn::http1,
+    service::service_fn,
+    Method, Request, Response, StatusCode,
+};
+use hyper_util::rt::TokioIo;
+use prometheus::{
+    core::Collector, Histogram, HistogramOpts, IntCounter, IntCounterVec, IntGauge, Opts, Registry,
+    TextEncoder, TEXT_FORMAT,
+};
+use snafu::{ResultExt, Snafu};
+use stackable_operator::kube::runtime::reflector::Store;
+use tokio::net::TcpListener;
+
+use crate::crd::EDCCluster;
+
+/// Upper bounds of the buckets of the reconcile duration histogram in seconds
+const RECONCILE_DURATION_BUCKETS: [f64; 11] =
+    [0.01, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0];
+
+#[derive(Snafu, Debug)]
+pub enum Error {
+    #[snafu(display("failed to register the metric [{name}]"))]
+    RegisterMetric {
+        source: prometheus::Error,
+        name: &'static str,
+    },
+    #[snafu(display("failed to encode the metrics"))]
+    EncodeMetrics { source: prometheus::Error },
+    #[snafu(display("failed to bind the metrics server to [{address}]"))]
+    Bind {
+        source: std::io::Error,
+        address: SocketAddr,
+    },
+}
+
+/// Metrics recorded during the reconciliations
+pub struct Metrics {
+    registry: Registry,
+    reconciles: IntCounter,
+    reconcile_duration: Histogram,
+    /// Number of failed reconciliations per error category
+    reconcile_errors: IntCounterVec,
+    managed_clusters: IntGauge,
+    managed_rolegroups: IntGauge,
+}
+
+impl Metrics {
+    pub fn new() -> Result<Self, Error> {
+        let registry = Registry::new();
+
+        Ok(Self {
+            reconciles: register(
+                &registry,
+                "edc_operator_reconciles_total",
+                IntCounter::new(
+                    "edc_operator_reconciles_total",
+                    "Number of reconciliations of EDCClusters",
+                ),
+            )?,
+            reconcile_duration: register(
+                &registry,
+                "edc_operator_reconcile_duration_seconds",
+                Histogram::with_opts(
+                    HistogramOpts::new(
+                        "edc_operator_reconcile_duration_seconds",
+                        "Duration of the reconciliations of EDCClusters",
+                    )
+                    .buckets(RECONCILE_DURATION_BUCKETS.to_vec()),
+                ),
+            )?,
+            reconcile_errors: register(
+                &registry,
+                "edc_operator_reconcile_errors_total",
+                IntCounterVec::new(
+                    Opts::new(
+                        "edc_operator_reconcile_errors_total",
+                        "Number of failed reconciliations of EDCClusters by error category",
+                    ),
+                    &["category"],
+                ),
+            )?,
+            managed_clusters: register(
+                &registry,
+                "edc_operator_managed_clusters",
+                IntGauge::new(
+                    "edc_operator_managed_clusters",
+                    "Number of EDCClusters managed by the operator",
+                ),
+            )?,
+            managed_rolegroups: register(
+                &registry,
+                "edc_operator_managed_rolegroups",
+                IntGauge::new(
+                    "edc_operator_managed_rolegroups",
+                    "Number of rolegroups of the EDCClusters managed by the operator",
+                ),
+            )?,
+            registry,
+        })
+    }
+
+    /// Record a reconciliation which took `duration` and failed with an error of the given
+    /// category if any
+    pub fn record_reconcile(&self, duration: Duration, error_category: Option<&'static str>) {
+        self.reconciles.inc();
+        self.reconcile_duration.observe(duration.as_secs_f64());
+        if let Some(category) = error_category {
+            self.reconcile_errors.with_label_values(&[category]).inc();
+        }
+    }
+
+    /// Render the metrics in the Prometheus text format, the managed EDCClusters are counted
+    /// from the given ones
+    fn render(&self, edcs: &[Arc<EDCCluster>]) -> Result<String, Error> {
+        let rolegroups = edcs
+            .iter()
+            .filter_map(|edc| edc.spec.connectors.as_ref())
+            .map(|role| role.role_groups.len())
+            .sum::<usize>();
+        self.managed_clusters.set(edcs.len() as i64);
+        self.managed_rolegroups.set(rolegroups as i64);
+
+        TextEncoder::new()
+            .encode_to_string(&self.registry.gather())
+            .context(EncodeMetricsSnafu)
+    }
+}
+
+/// Register the created metric in the registry
+fn register<M>(
+    registry: &Registry,
+    name: &'static str,
+    metric: prometheus::Result<M>,
+) -> Result<M, Error>
+where
+    M: Collector + Clone + 'static,
+{
+    let metric = metric.context(RegisterMetricSnafu { name })?;
+    registry
+        .register(Box::new(metric.clone()))
+        .context(RegisterMetricSnafu { name })?;
+    Ok(metric)
+}
+
+/// Serve the metrics under `/metrics` on the given address until the task is aborted
+pub async fn serve(
+    address: SocketAddr,
+    metrics: Arc<Metrics>,
+    edc_store: Store<EDCCluster>,
+) -> Result<(), Error> {
+    let listener = TcpListener::bind(address)
+        .await
+        .context(BindSnafu { address })?;
+    tracing::info!(%address, "Serving the operator metrics");
+
+    loop {
+        let stream = match listener.accept().await {
+            Ok((stream, _)) => stream,
+            Err(error) => {
+                tracing::warn!(
+                    error = &error as &dyn std::error::Error,
+                    "Failed to accept a connection to the metrics server",
+                );
+                continue;
+            }
+        };
+
+        let metrics = metrics.clone();
+        let edc_store = edc_store.clone();
+        tokio::spawn(async move {
+            let service = service_fn(move |request| {
+                let response = handle_request(&request, &metrics, &edc_store);
+                async move { Ok::<_, Infallible>(response) }
+            });
+            if let Err(error) = http1::Builder::new()
+                .serve_connection(TokioIo::new(stream), service)
+                .await
+            {
+                tracing::debug!(
+                    error = &error as &dyn std::error::Error,
+                    "Failed to serve a connection to the metrics server",
+                );
+            }
+        });
+    }
+}
+
+fn handle_request(
+    request: &Request<Incoming>,
+    metrics: &Metrics,
+    edc_store: &Store<EDCCluster>,
+) -> Response<Full<Bytes>> {
+    let response = if request.method() == Method::GET && request.uri().path() == "/metrics" {
+        match metrics.render(&edc_store.state()) {
+            Ok(output) => Response::builder()
+                .header(CONTENT_TYPE, TEXT_FORMAT)
+                .body(Full::new(Bytes::from(output))),
+            Err(error) => {
+                tracing::warn!(
+                    error = &error as &dyn std::error::Error,
+                    "Failed to render the operator metrics",
+                );
+                Response::builder()
+                    .status(StatusCode::INTERNAL_SERVER_ERROR)
+                    .body(Full::new(Bytes::new()))
+            }
+        }
+    } else {
+        Response::builder()
+            .status(StatusCode::NOT_FOUND)
+            .body(Full::new(Bytes::new()))
+    };
+    response.expect("the response is valid")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fixtures::edc_cluster;
+
+    #[test]
+    fn test_render() {
+        let edc = edc_cluster(
+            "
+            roleGroups:
+              control:
+                replicas: 1
+              data:
+                replicas: 2
+            ",
+        );
+
+        let metrics = Metrics::new().unwrap();
+        metrics.record_reconcile(Duration::from_millis(30), None);
+        metrics.record_reconcile(Duration::from_millis(200), Some("ResolveS3Connection"));
+        metrics.record_reconcile(Duration::from_secs(120), Some("ResolveS3Connection"));
+
+        let output = metrics.render(&[Arc::new(edc)]).unwrap();
+
+        assert!(output.contains("edc_operator_reconciles_total 3\n"));
+        assert!(output.contains("edc_operator_reconcile_duration_seconds_bucket{le=\"0.01\"} 0\n"));
+        assert!(output.contains("edc_operator_reconcile_duration_seconds_bucket{le=\"0.05\"} 1\n"));
+        assert!(output.contains("edc_operator_reconcile_duration_seconds_bucket{le=\"60\"} 2\n"));
+        assert!(output.contains("edc_operator_reconcile_duration_seconds_bucket{le=\"+Inf\"} 3\n"));
+        assert!(output.contains("edc_operator_reconcile_duration_seconds_count 3\n"));
+        assert!(output
+            .contains("edc_operator_reconcile_errors_total{category=\"ResolveS3Connection\"} 2\n"));
+        assert!(output.contains("edc_operator_managed_clusters 1\n"));
+        assert!(output.contains("edc_operator_managed_rolegroups 2\n"));
+    }
+}
@@ -0,0 +1,459 @@
+//! NetworkPolicy which isolates the connectors, so that only the dataspace can call the protocol
+//! and public APIs and only selected workloads can call the management and control APIs
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use hyper::http::uri::{Authority, InvalidUri, Uri};
+use product_config::types::PropertyNameKind;
+use snafu::{OptionExt, ResultExt, Snafu};
+use stackable_operator::{
+    builder::meta::ObjectMetaBuilder,
+    commons::{product_image_selection::ResolvedProductImage, s3::S3ConnectionSpec},
+    k8s_openapi::{
+        api::networking::v1::{
+            NetworkPolicy, NetworkPolicyEgressRule, NetworkPolicyIngressRule, NetworkPolicyPeer,
+            NetworkPolicyPort, NetworkPolicySpec,
+        },
+        apimachinery::pkg::{apis::meta::v1::LabelSelector, util::intstr::IntOrString},
+    },
+    kube::ResourceExt,
+    kvp::{LabelError, Labels},
+};
+
+use crate::{
+    controller::build_recommended_labels,
+    crd::{
+        EDCCluster, EDCRole, APP_NAME, CONFIG_PROPERTIES, DEFAULT_WEB_CONTEXT,
+        EDC_VAULT_HASHICORP_URL, METRICS_PORT, WEB_CONTEXTS,
+    },
+};
+
+const DNS_PORT: u16 = 53;
+const HTTP_PORT: u16 = 80;
+const HTTPS_PORT: u16 = 443;
+/// Label of the namespace name, which Kubernetes sets on every namespace
+const NAMESPACE_NAME_LABEL: &str = "kubernetes.io/metadata.name";
+
+#[derive(Snafu, Debug)]
+pub enum Error {
+    #[snafu(display("object is missing metadata to build owner reference"))]
+    ObjectMissingMetadataForOwnerRef {
+        source: stackable_operator::builder::meta::Error,
+    },
+    #[snafu(display("failed to build object meta data"))]
+    ObjectMeta {
+        source: stackable_operator::builder::meta::Error,
+    },
+    #[snafu(display("failed to build the Pod selector"))]
+    BuildLabel { source: LabelError },
+    #[snafu(display("failed to resolve the config of rolegroup [{rolegroup}]"))]
+    ResolveConfig {
+        source: crate::crd::Error,
+        rolegroup: String,
+    },
+    #[snafu(display("the vault URL [{url}] is invalid"))]
+    InvalidVaultUrl { source: InvalidUri, url: String },
+    #[snafu(display("the vault URL [{url}] has neither a port nor the scheme http or https"))]
+    UnknownVaultPort { url: String },
+    #[snafu(display("the Vector aggregator address [{address}] is invalid"))]
+    InvalidVectorAggregatorAddress { source: InvalidUri, address: String },
+    #[snafu(display("the Vector aggregator address [{address}] has no port"))]
+    MissingVectorAggregatorPort { address: String },
+}
+
+type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// Name of the NetworkPolicy of the given role
+pub fn network_policy_name(edc: &EDCCluster, role: &EDCRole) -> String {
+    format!("{}-{role}", edc.name_any())
+}
+
+/// Build the NetworkPolicy of the connectors if it is enabled in the `clusterConfig`
+///
+/// The ingress rules are derived from the web contexts, which are also exposed by the Services.
+/// The external contexts can be called from the configured external sources and the other
+/// contexts and the metrics only from the management sources. The operator, which runs in the
+/// given namespace, may always call the observability API for its health checks. Egress is only
+/// allowed to DNS, the vault, the S3 storage and the Vector aggregator if the Vector agent is
+/// enabled.
+pub fn build_network_policy(
+    edc: &EDCCluster,
+    resolved_product_image: &ResolvedProductImage,
+    server_config: &HashMap<String, HashMap<PropertyNameKind, BTreeMap<String, String>>>,
+    s3_conn: Option<&S3ConnectionSpec>,
+    vector_aggregator_address: Option<&str>,
+    operator_namespace: Option<&str>,
+) -> Result<Option<NetworkPolicy>> {
+    let cluster_config = &edc.spec.cluster_config;
+    let network_policy_config = &cluster_config.network_policy;
+    if !network_policy_config.enabled {
+        return Ok(None);
+    }
+
+    let role = EDCRole::Connector;
+    let role_name = role.to_string();
+
+    let metadata = ObjectMetaBuilder::new()
+        .name_and_namespace(edc)
+        .name(network_policy_name(edc, &role))
+        .ownerreference_from_resource(edc, None, Some(true))
+        .context(ObjectMissingMetadataForOwnerRefSnafu)?
+        .with_recommended_labels(build_recommended_labels(
+            edc,
+            &resolved_product_image.app_version_label,
+            &role_name,
+            "global",
+        ))
+        .context(ObjectMetaSnafu)?
+        .build();
+
+    let pod_selector = LabelSelector {
+        match_labels: Some(
+            Labels::role_selector(edc, APP_NAME, &role_name)
+                .context(BuildLabelSnafu)?
+                .into(),
+        ),
+        ..LabelSelector::default()
+    };
+
+    let (external_contexts, internal_contexts): (Vec<_>, Vec<_>) =
+        WEB_CONTEXTS.iter().partition(|context| context.external);
+
+    let mut internal_ports = internal_contexts
+        .into_iter()
+        .map(|context| network_policy_port(context.port, "TCP"))
+        .collect::<Vec<_>>();
+    if cluster_config.metrics.enabled {
+        internal_ports.push(network_policy_port(METRICS_PORT, "TCP"));
+    }
+
+    let mut ingress = vec![NetworkPolicyIngressRule {
+        from: peers(&network_policy_config.external_sources),
+        ports: Some(
+            external_contexts
+                .into_iter()
+                .map(|context| network_policy_port(context.port, "TCP"))
+                .collect(),
+        ),
+    }];
+    // Without management sources, the internal contexts cannot be called at all instead of
+    // being callable from everywhere
+    if !network_policy_config.management_sources.is_empty() {
+        ingress.push(NetworkPolicyIngressRule {
+            from: Some(network_policy_config.management_sources.clone()),
+            ports: Some(internal_ports),
+        });
+    }
+
+    if let Some(operator_namespace) = operator_namespace {
+        ingress.push(NetworkPolicyIngressRule {
+            from: Some(vec![NetworkPolicyPeer {
+                namespace_selector: Some(LabelSelector {
+                    match_labels: Some(BTreeMap::from([(
+                        NAMESPACE_NAME_LABEL.to_string(),
+                        operator_namespace.to_string(),
+                    )])),
+                    ..LabelSelector::default()
+                }),
+                ..NetworkPolicyPeer::default()
+            }]),
+            ports: Some(vec![network_policy_port(DEFAULT_WEB_CONTEXT.port, "TCP")]),
+        });
+    }
+
+    let mut egress = vec![NetworkPolicyEgressRule {
+        to: None,
+        ports: Some(vec![
+            network_policy_port(DNS_PORT, "UDP"),
+            network_policy_port(DNS_PORT, "TCP"),
+        ]),
+    }];
+    let vault_ports = vault_ports(server_config)?;
+    if !vault_ports.is_empty() {
+        egress.push(NetworkPolicyEgressRule {
+            to: peers(&network_policy_config.vault_destinations),
+            ports: Some(
+                vault_ports
+                    .into_iter()
+                    .map(|port| network_policy_port(port, "TCP"))
+                    .collect(),
+            ),
+        });
+    }
+    if let Some(s3_conn) = s3_conn {
+        egress.push(NetworkPolicyEgressRule {
+            to: peers(&network_policy_config.s3_destinations),
+            ports: Some(vec![network_policy_port(s3_port(s3_conn), "TCP")]),
+        });
+    }
+    if let Some(vector_aggregator_address) = vector_aggregator_address {
+        if vector_agent_enabled(edc, server_config)? {
+            egress.push(NetworkPolicyEgressRule {
+                to: None,
+                ports: Some(vec![network_policy_port(
+                    vector_aggregator_port(vector_aggregator_address)?,
+                    "TCP",
+                )]),
+            });
+        }
+    }
+
+    Ok(Some(NetworkPolicy {
+        metadata,
+        spec: Some(NetworkPolicySpec {
+            pod_selector,
+            policy_types: Some(vec!["Ingress".to_string(), "Egress".to_string()]),
+            ingress: Some(ingress),
+            egress: Some(egress),
+        }),
+    }))
+}
+
+/// The given peers or all peers if none are given
+fn peers(peers: &[NetworkPolicyPeer]) -> Option<Vec<NetworkPolicyPeer>> {
+    (!peers.is_empty()).then(|| peers.to_vec())
+}
+
+fn network_policy_port(port: u16, protocol: &str) -> NetworkPolicyPort {
+    NetworkPolicyPort {
+        port: Some(IntOrString::Int(port.into())),
+        protocol: Some(protocol.to_string()),
+        end_port: None,
+    }
+}
+
+fn s3_port(s3_conn: &S3ConnectionSpec) -> u16 {
+    s3_conn.port.unwrap_or(if s3_conn.tls.is_some() {
+        HTTPS_PORT
+    } else {
+        HTTP_PORT
+    })
+}
+
+/// The ports of the vault URLs configured in the rolegroups
+fn vault_ports(
+    server_config: &HashMap<String, HashMap<PropertyNameKind, BTreeMap<String, String>>>,
+) -> Result<BTreeSet<u16>> {
+    server_config
+        .values()
+        .filter_map(|rolegroup_config| {
+            rolegroup_config
+                .get(&PropertyNameKind::File(CONFIG_PROPERTIES.to_string()))?
+                .get(EDC_VAULT_HASHICORP_URL)
+        })
+        .map(|url| vault_port(url))
+        .collect()
+}
+
+fn vault_port(url: &str) -> Result<u16> {
+    let uri = url.parse::<Uri>().context(InvalidVaultUrlSnafu { url })?;
+    uri.port_u16()
+        .or(match uri.scheme_str() {
+            Some("http") => Some(HTTP_PORT),
+            Some("https") => Some(HTTPS_PORT),
+            _ => None,
+        })
+        .context(UnknownVaultPortSnafu { url })
+}
+
+/// The port of the Vector aggregator address, which has the form `host:port`
+fn vector_aggregator_port(address: &str) -> Result<u16> {
+    address
+        .parse::<Authority>()
+        .context(InvalidVectorAggregatorAddressSnafu { address })?
+        .port_u16()
+        .context(MissingVectorAggregatorPortSnafu { address })
+}
+
+/// Whether the Vector agent is enabled in any rolegroup
+fn vector_agent_enabled(
+    edc: &EDCCluster,
+    server_config: &HashMap<String, HashMap<PropertyNameKind, BTreeMap<String, String>>>,
+) -> Result<bool> {
+    for rolegroup in server_config.keys() {
+        let config = edc
+            .merged_config(&EDCRole::Connector, rolegroup)
+            .context(ResolveConfigSnafu { rolegroup })?;
+        if config.logging.enable_vector_agent {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{controller::DOCKER_IMAGE_BASE_NAME, test_fixtures::edc_cluster_with_spec};
+
+    /// EDCCluster with one rolegroup and the given `clusterConfig` merged into the default one
+    fn edc_cluster(cluster_config: &str) -> EDCCluster {
+        edc_cluster_with_spec(
+            &format!("clusterConfig: {{{cluster_config}}}"),
+            "roleGroups: {default: {replicas: 1}}",
+        )
+    }
+
+    fn build(edc: &EDCCluster, s3_conn: Option<&S3ConnectionSpec>) -> Option<NetworkPolicySpec> {
+        build_with(edc, s3_conn, None, None)
+    }
+
+    fn build_with(
+        edc: &EDCCluster,
+        s3_conn: Option<&S3ConnectionSpec>,
+        vector_aggregator_address: Option<&str>,
+        operator_namespace: Option<&str>,
+    ) -> Option<NetworkPolicySpec> {
+        let resolved_product_image = edc.spec.image.resolve(DOCKER_IMAGE_BASE_NAME, "0.0.0-dev");
+        let server_config = HashMap::from([(
+            "default".to_string(),
+            HashMap::from([(
+                PropertyNameKind::File(CONFIG_PROPERTIES.to_string()),
+                BTreeMap::from([(
+                    EDC_VAULT_HASHICORP_URL.to_string(),
+                    "https://vault.vault.svc.cluster.local:8200".to_string(),
+                )]),
+            )]),
+        )]);
+        build_network_policy(
+            edc,
+            &resolved_product_image,
+            &server_config,
+            s3_conn,
+            vector_aggregator_address,
+            operator_namespace,
+        )
+        .expect("failed to build the NetworkPolicy")
+        .map(|network_policy| network_policy.spec.unwrap())
+    }
+
+    fn ports(ports: &Option<Vec<NetworkPolicyPort>>) -> Vec<i32> {
+        ports
+            .iter()
+            .flatten()
+            .filter_map(|port| match port.port {
+                Some(IntOrString::Int(port)) => Some(port),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_disabled_network_policy() {
+        assert_eq!(None, build(&edc_cluster(""), None));
+    }
+
+    #[test]
+    fn test_network_policy() {
+        let edc = edc_cluster(
+            "
+            metrics: {enabled: true},
+            networkPolicy: {
+              enabled: true,
+              externalSources: [{ipBlock: {cidr: 203.0.113.0/24}}],
+              managementSources: [
+                {namespaceSelector: {matchLabels: {kubernetes.io/metadata.name: backend}}}
+              ]
+            }
+            ",
+        );
+        let s3_conn: S3ConnectionSpec =
+            serde_yaml::from_str("host: s3-eu-central-1.ionoscloud.com").unwrap();
+
+        let spec = build(&edc, Some(&s3_conn)).unwrap();
+
+        let ingress = spec.ingress.unwrap();
+        assert_eq!(2, ingress.len());
+        assert_eq!(vec![8282, 8284], ports(&ingress[0].ports));
+        assert_eq!(
+            Some("203.0.113.0/24"),
+            ingress[0].from.as_ref().unwrap()[0]
+                .ip_block
+                .as_ref()
+                .map(|ip_block| ip_block.cidr.as_str())
+        );
+        assert_eq!(vec![8181, 8283, 8182, 9404], ports(&ingress[1].ports));
+        assert!(ingress[1].from.as_ref().unwrap()[0]
+            .namespace_selector
+            .is_some());
+
+        let egress = spec.egress.unwrap();
+        assert_eq!(
+            vec![vec![53, 53], vec![8200], vec![80]],
+            egress
+                .iter()
+                .map(|rule| ports(&rule.ports))
+                .collect::<Vec<_>>()
+        );
+        assert!(egress.iter().all(|rule| rule.to.is_none()));
+    }
+
+    #[test]
+    fn test_network_policy_without_management_sources() {
+        let edc = edc_cluster("networkPolicy: {enabled: true}");
+
+        let ingress = build(&edc, None).unwrap().ingress.unwrap();
+
+        assert_eq!(1, ingress.len());
+        assert_eq!(None, ingress[0].from);
+    }
+
+    #[test]
+    fn test_network_policy_for_operator_and_vector() {
+        let edc = edc_cluster_with_spec(
+            "
+            clusterConfig:
+              vectorAggregatorConfigMapName: vector-aggregator-discovery
+              networkPolicy:
+                enabled: true
+            ",
+            "
+            config:
+              logging:
+                enableVectorAgent: true
+            roleGroups:
+              default:
+                replicas: 1
+            ",
+        );
+
+        let spec = build_with(
+            &edc,
+            None,
+            Some("vector-aggregator:6000"),
+            Some("stackable-operators"),
+        )
+        .unwrap();
+
+        let ingress = spec.ingress.unwrap();
+        assert_eq!(2, ingress.len());
+        assert_eq!(vec![8181], ports(&ingress[1].ports));
+        assert_eq!(
+            Some(&"stackable-operators".to_string()),
+            ingress[1].from.as_ref().unwrap()[0]
+                .namespace_selector
+                .as_ref()
+                .and_then(|selector| selector.match_labels.as_ref())
+                .and_then(|labels| labels.get(NAMESPACE_NAME_LABEL))
+        );
+
+        let egress = spec.egress.unwrap();
+        assert_eq!(
+            vec![vec![53, 53], vec![8200], vec![6000]],
+            egress
+                .iter()
+                .map(|rule| ports(&rule.ports))
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_vault_port() {
+        assert_eq!(8200, vault_port("http://consumer-vault:8200").unwrap());
+        assert_eq!(443, vault_port("https://vault.example.com").unwrap());
+        assert_eq!(80, vault_port("http://vault").unwrap());
+        assert!(matches!(
+            vault_port("vault"),
+            Err(Error::UnknownVaultPort { .. })
+        ));
+    }
+}
@@ -0,0 +1,103 @@
+//! PodDisruptionBudgets which keep node drains from evicting all connectors of a role at once
+use snafu::{ResultExt, Snafu};
+use stackable_operator::{
+    builder::pdb::PodDisruptionBudgetBuilder, k8s_openapi::api::policy::v1::PodDisruptionBudget,
+};
+
+use crate::{
+    controller::EDC_CONTROLLER_NAME,
+    crd::{EDCCluster, EDCRole, APP_NAME},
+    OPERATOR_NAME,
+};
+
+/// Number of connectors which may be unavailable at the same time if `maxUnavailable` is not set
+const CONNECTOR_MAX_UNAVAILABLE: u16 = 1;
+
+#[derive(Snafu, Debug)]
+pub enum Error {
+    #[snafu(display("failed to build the PodDisruptionBudget of the role [{role}]"))]
+    BuildPdb {
+        source: stackable_operator::builder::pdb::Error,
+        role: String,
+    },
+}
+
+/// Build the PodDisruptionBudget of the given role if the role is defined and the
+/// PodDisruptionBudget is enabled in its `roleConfig`
+///
+/// If no PodDisruptionBudget is built, then an existing one is deleted as orphaned resource.
+pub fn build_pdb(edc: &EDCCluster, role: &EDCRole) -> Result<Option<PodDisruptionBudget>, Error> {
+    let Some(pdb_config) = edc
+        .get_role(role)
+        .map(|role| &role.role_config.pod_disruption_budget)
+        .filter(|pdb_config| pdb_config.enabled)
+    else {
+        return Ok(None);
+    };
+
+    let max_unavailable = pdb_config.max_unavailable.unwrap_or(match role {
+        EDCRole::Connector => CONNECTOR_MAX_UNAVAILABLE,
+    });
+
+    let pdb = PodDisruptionBudgetBuilder::new_with_role(
+        edc,
+        APP_NAME,
+        &role.to_string(),
+        OPERATOR_NAME,
+        EDC_CONTROLLER_NAME,
+    )
+    .with_context(|_| BuildPdbSnafu {
+        role: role.to_string(),
+    })?
+    .with_max_unavailable(max_unavailable)
+    .build();
+
+    Ok(Some(pdb))
+}
+
+#[cfg(test)]
+mod tests {
+    use stackable_operator::k8s_openapi::apimachinery::pkg::util::intstr::IntOrString;
+
+    use super::*;
+
+    /// EDCCluster with three replicas and the given `roleConfig` of the connectors
+    fn edc_cluster(role_config: &str) -> EDCCluster {
+        test_fixtures::edc_cluster(&format!(
+            "{{roleConfig: {{{role_config}}}, roleGroups: {{default: {{replicas: 3}}}}}}"
+        ))
+    }
+
+    fn build_connector_pdb(edc: &EDCCluster) -> Option<PodDisruptionBudget> {
+        build_pdb(edc, &EDCRole::Connector).expect("failed to build the PodDisruptionBudget")
+    }
+
+    #[test]
+    fn test_default_pdb() {
+        let edc = edc_cluster("");
+
+        let pdb = build_connector_pdb(&edc).unwrap();
+        let spec = pdb.spec.unwrap();
+
+        assert_eq!(Some("provider-server".to_string()), pdb.metadata.name);
+        assert_eq!(Some(IntOrString::Int(1)), spec.max_unavailable);
+        assert_eq!(
+            Some("server"),
+            spec.selector
+                .and_then(|selector| selector.match_labels)
+                .unwrap_or_default()
+                .get("app.kubernetes.io/component")
+                .map(String::as_str)
+        );
+    }
+
+    #[test]
+    fn test_configured_pdb() {
+        let edc = edc_cluster("podDisruptionBudget: {maxUnavailable: 2}");
+        let spec = build_connector_pdb(&edc).unwrap().spec.unwrap();
+        assert_eq!(Some(IntOrString::Int(2)), spec.max_unavailable);
+
+        let edc = edc_cluster("podDisruptionBudget: {enabled: false}");
+        assert_eq!(None, build_connector_pdb(&edc));
+    }
+}
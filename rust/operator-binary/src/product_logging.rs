@@ -43,7 +43,28 @@ pub enum Error {
 type Result<T, E = Error> = std::result::Result<T, E>;
 
 const VECTOR_AGGREGATOR_CM_ENTRY: &str = "ADDRESS";
-const CONSOLE_CONVERSION_PATTERN: &str = "%d{ISO8601} %5p [%t] %c{2}: %m%n";
+/// Format of the `java.util.logging.SimpleFormatter` used for the console, the arguments are the date, the source, the
+/// logger name, the level, the message and the thrown exception
+const CONSOLE_CONVERSION_PATTERN: &str = "%1$tFT%1$tT.%1$tL%1$tz %4$s [%3$s] %5$s%6$s%n";
+const JAVA_UTIL_LOGGING_LOG_FILE: &str = "edc";
+/// Number of files the `java.util.logging.FileHandler` rotates through
+const JAVA_UTIL_LOGGING_LOG_FILE_COUNT: u32 = 2;
+/// Every record written by the `java.util.logging.XMLFormatter` spans several lines starting with
+/// `<record>` and ending with `</record>`
+const JUL_RECORD_START_PATTERN: &str = "^<record>$";
+const JUL_RECORD_END_PATTERN: &str = "^</record>$";
+/// Levels of `java.util.logging` and the log levels they are mapped to
+const JAVA_UTIL_LOGGING_LEVELS: [(&str, &str); 7] = [
+    ("SEVERE", "ERROR"),
+    ("WARNING", "WARN"),
+    ("INFO", "INFO"),
+    ("CONFIG", "INFO"),
+    ("FINE", "DEBUG"),
+    ("FINER", "TRACE"),
+    ("FINEST", "TRACE"),
+];
+/// Log level of records with a custom level of `java.util.logging`
+const JAVA_UTIL_LOGGING_CUSTOM_LEVEL: &str = "TRACE";
 
 /// Return the address of the Vector aggregator if the corresponding ConfigMap name is given in the
 /// cluster spec
@@ -94,7 +115,7 @@ pub fn extend_role_group_config_map(
             EDC_CONNECTOR_JAVA_LOG_FILE,
             create_java_logging_config(
                 &format!("{STACKABLE_LOG_DIR}/edc"),
-                EDC_CONNECTOR_JAVA_LOG_FILE,
+                JAVA_UTIL_LOGGING_LOG_FILE,
                 MAX_LOG_FILES_SIZE
                     .scale_to(BinaryMultiple::Mebi)
                     .floor()
@@ -129,28 +150,71 @@ pub fn extend_role_group_config_map(
 }
 
 fn create_java_logging_config(
-    _log_dir: &str,
-    _log_file: &str,
-    _max_size_in_mib: u32,
-    _console_conversion_pattern: &str,
-    _config: &AutomaticContainerLogConfig,
+    log_dir: &str,
+    log_file: &str,
+    max_size_in_mib: u32,
+    console_conversion_pattern: &str,
+    config: &AutomaticContainerLogConfig,
 ) -> String {
+    let loggers = config
+        .loggers
+        .iter()
+        .filter(|(name, _)| name.as_str() != AutomaticContainerLogConfig::ROOT_LOGGER)
+        .map(|(name, logger_config)| {
+            format!(
+                "{name}.level={level}\n",
+                level = to_java_util_logging_level(logger_config.level)
+            )
+        })
+        .collect::<String>();
+
     format!(
         r#"handlers=java.util.logging.FileHandler, java.util.logging.ConsoleHandler
 .level={root_log_level}
-java.util.logging.FileHandler.pattern=/stackable/log/edc/edc.%g.logger
-java.util.logging.FileHandler.limit=50000
-java.util.logging.FileHandler.count=10
-java.util.logging.FileHandler.formatter=java.util.logging.SimpleFormatter
+{loggers}
+java.util.logging.FileHandler.pattern={log_dir}/{log_file}.%g.logger
+java.util.logging.FileHandler.limit={max_log_file_size_in_bytes}
+java.util.logging.FileHandler.count={JAVA_UTIL_LOGGING_LOG_FILE_COUNT}
+java.util.logging.FileHandler.level={file_log_level}
+java.util.logging.FileHandler.formatter=java.util.logging.XMLFormatter
 java.util.logging.ConsoleHandler.level={console_log_level}
-java.util.logging.ConsoleHandler.formatter = java.util.logging.SimpleFormatter
-
+java.util.logging.ConsoleHandler.formatter=java.util.logging.SimpleFormatter
+java.util.logging.SimpleFormatter.format={console_conversion_pattern}
 "#,
-        root_log_level = "INFO",
-        console_log_level = "INFO"
+        root_log_level = to_java_util_logging_level(config.root_log_level()),
+        // The size limit applies to each of the rotated files
+        max_log_file_size_in_bytes =
+            max_size_in_mib * 1024 * 1024 / JAVA_UTIL_LOGGING_LOG_FILE_COUNT,
+        file_log_level = to_java_util_logging_level(
+            config
+                .file
+                .as_ref()
+                .and_then(|file| file.level)
+                .unwrap_or_default()
+        ),
+        console_log_level = to_java_util_logging_level(
+            config
+                .console
+                .as_ref()
+                .and_then(|console| console.level)
+                .unwrap_or_default()
+        ),
     )
 }
 
+/// Convert a log level into the corresponding level of `java.util.logging`
+fn to_java_util_logging_level(level: LogLevel) -> &'static str {
+    match level {
+        LogLevel::TRACE => "FINEST",
+        LogLevel::DEBUG => "FINE",
+        LogLevel::INFO => "INFO",
+        LogLevel::WARN => "WARNING",
+        LogLevel::ERROR => "SEVERE",
+        LogLevel::FATAL => "SEVERE",
+        LogLevel::NONE => "OFF",
+    }
+}
+
 fn create_vector_config(
     role_group: &RoleGroupRef<EDCCluster>,
     vector_aggregator_address: &str,
@@ -192,6 +256,12 @@ include = ["{STACKABLE_LOG_DIR}/*/*.stderr.log"]
 type = "file"
 include = ["{STACKABLE_LOG_DIR}/*/*.logger"]
 
+[sources.files_jul.multiline]
+mode = "halt_with"
+start_pattern = "{JUL_RECORD_START_PATTERN}"
+condition_pattern = "{JUL_RECORD_END_PATTERN}"
+timeout_ms = 1000
+
 [transforms.processed_files_stdout]
 inputs = ["files_stdout"]
 type = "remap"
@@ -212,8 +282,57 @@ source = '''
 inputs = ["files_jul"]
 type = "remap"
 source = '''
-.logger = "ROOT"
+raw_message = string!(.message)
+
+# The XML prolog and the enclosing log element are not log records
+if !starts_with(raw_message, "<record>") {{
+  abort
+}}
+
+.timestamp = now()
+.logger = ""
 .level = "INFO"
+.thread = ""
+.message = ""
+.errors = []
+
+parsed_event, err = parse_xml(raw_message)
+if err != null {{
+  error = "XML not parsable: " + err
+  .errors = push(.errors, error)
+  log(error, level: "warn")
+  .message = raw_message
+}} else {{
+  record = object!(parsed_event.record)
+
+  date, err = string(record.date)
+  if err == null {{
+    timestamp, err = parse_timestamp(date, "%Y-%m-%dT%H:%M:%S%.fZ")
+    if err == null {{
+      .timestamp = timestamp
+    }} else {{
+      .errors = push(.errors, "Timestamp not parsable: " + err)
+    }}
+  }} else {{
+    .errors = push(.errors, "Date not found")
+  }}
+
+  .logger = to_string(record.logger) ?? ""
+  .thread = to_string(record.thread) ?? ""
+
+  level, err = string(record.level)
+  if err == null {{
+    .level = {jul_level_expression}
+  }} else {{
+    .errors = push(.errors, "Level not found")
+  }}
+
+  .message = to_string(record.message) ?? ""
+  if record.exception != null {{
+    exception = to_string(record.exception.message) ?? ""
+    .message = .message + "\n" + exception
+  }}
+}}
 '''
 
 [transforms.parsed_logs_std]
@@ -224,7 +343,7 @@ source = '''
 del(.source_type)
 '''
 
-[transforms.parsed_logs_jul]
+[transforms.extended_logs_jul]
 inputs = ["processed_files_jul"]
 type = "remap"
 source = '''
@@ -245,13 +364,6 @@ if err == null {{
 }}
 '''
 
-[transforms.extended_logs_jul]
-inputs = ["parsed_logs_jul"]
-type = "remap"
-source = '''
-.message = "Java Logging: " + string!(.message)
-'''
-
 [transforms.filtered_logs_vector]
 inputs = ["vector"]
 type = "filter"
@@ -288,6 +400,355 @@ address = "{vector_aggregator_address}"
         namespace = role_group.cluster.namespace.clone().unwrap_or_default(),
         cluster_name = role_group.cluster.name,
         role_name = role_group.role,
-        role_group_name = role_group.role_group
+        role_group_name = role_group.role_group,
+        jul_level_expression = jul_level_expression(),
     )
 }
+
+/// Render a VRL expression which maps the JUL level in the variable `level` to the corresponding
+/// log level
+fn jul_level_expression() -> String {
+    let conditions = JAVA_UTIL_LOGGING_LEVELS
+        .iter()
+        .map(|(jul_level, level)| format!(r#"if level == "{jul_level}" {{ "{level}" }}"#))
+        .collect::<Vec<_>>()
+        .join(" else ");
+
+    format!(r#"{conditions} else {{ "{JAVA_UTIL_LOGGING_CUSTOM_LEVEL}" }}"#)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use regex::Regex;
+    use stackable_operator::{
+        k8s_openapi::chrono::NaiveDateTime,
+        product_logging::spec::{AppenderConfig, LoggerConfig},
+    };
+    use xml::reader::{EventReader, XmlEvent};
+
+    use super::*;
+    use crate::test_fixtures::edc_cluster;
+
+    /// Log file as written by the `java.util.logging.XMLFormatter`
+    ///
+    /// The dates are formatted with `DateTimeFormatter.ISO_INSTANT`, which omits the fractional
+    /// seconds if they are zero.
+    const JUL_LOG_FILE: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="no"?>
+<!DOCTYPE log SYSTEM "logger.dtd">
+<log>
+<record>
+  <date>2024-06-03T09:22:39.123456Z</date>
+  <millis>1717406559123</millis>
+  <nanos>456000</nanos>
+  <sequence>42</sequence>
+  <logger>org.eclipse.edc.boot.system.ExtensionLoader</logger>
+  <level>WARNING</level>
+  <class>org.eclipse.edc.boot.system.ExtensionLoader</class>
+  <method>bootServiceExtensions</method>
+  <thread>1</thread>
+  <message>Config value: no setting found for &apos;edc.hostname&apos;</message>
+</record>
+<record>
+  <date>2024-06-03T09:22:40Z</date>
+  <millis>1717406560000</millis>
+  <nanos>0</nanos>
+  <sequence>43</sequence>
+  <logger>org.eclipse.edc.connector.dataplane</logger>
+  <level>SEVERE</level>
+  <class>org.eclipse.edc.connector.dataplane.DataPlaneManagerImpl</class>
+  <method>processTransfer</method>
+  <thread>27</thread>
+  <message>Transfer failed</message>
+  <exception>
+    <message>java.io.IOException: Connection reset</message>
+    <frame>
+      <class>sun.nio.ch.SocketChannelImpl</class>
+      <method>throwConnectionReset</method>
+      <line>401</line>
+    </frame>
+  </exception>
+</record>
+<record>
+  <date>2024-06-03T09:22:41.500Z</date>
+  <millis>1717406561500</millis>
+  <nanos>0</nanos>
+  <sequence>44</sequence>
+  <logger>org.eclipse.edc.boot</logger>
+  <level>AUDIT</level>
+  <class>org.eclipse.edc.boot.system.runtime.BaseRuntime</class>
+  <method>boot</method>
+  <thread>1</thread>
+  <message>Custom level</message>
+</record>
+</log>
+"#;
+
+    /// Fields of a record extracted by the `processed_files_jul` transform
+    #[derive(Debug, PartialEq)]
+    struct JulEvent {
+        timestamp: NaiveDateTime,
+        logger: String,
+        level: String,
+        thread: String,
+        message: String,
+    }
+
+    /// Quoted value of the first line in the rendered Vector config which starts with the given
+    /// prefix
+    fn vector_config_value<'a>(vector_config: &'a str, prefix: &str) -> &'a str {
+        let line = vector_config
+            .lines()
+            .find_map(|line| line.trim_start().strip_prefix(prefix))
+            .unwrap_or_else(|| panic!("{prefix} not found in the Vector config"));
+        line.split('"').nth(1).expect("value is not quoted")
+    }
+
+    /// Split the log file into events like the `halt_with` multiline mode of the Vector file
+    /// source
+    fn multiline_events(vector_config: &str, log_file: &str) -> Vec<String> {
+        let start_pattern =
+            Regex::new(vector_config_value(vector_config, "start_pattern =")).unwrap();
+        let condition_pattern =
+            Regex::new(vector_config_value(vector_config, "condition_pattern =")).unwrap();
+
+        let mut events = Vec::new();
+        let mut pending: Option<Vec<&str>> = None;
+        for line in log_file.lines() {
+            match pending.as_mut() {
+                Some(lines) => lines.push(line),
+                None if start_pattern.is_match(line) => pending = Some(vec![line]),
+                None => {
+                    events.push(line.to_string());
+                    continue;
+                }
+            }
+            if condition_pattern.is_match(line) {
+                events.extend(pending.take().map(|lines| lines.join("\n")));
+            }
+        }
+        events.extend(pending.map(|lines| lines.join("\n")));
+        events
+    }
+
+    /// Text of the elements in the given XML document keyed by their path, e.g.
+    /// `record/exception/message`
+    fn parse_xml(document: &str) -> BTreeMap<String, String> {
+        let mut elements = BTreeMap::new();
+        let mut path = Vec::new();
+        for event in EventReader::from_str(document) {
+            match event.expect("XML is parsable") {
+                XmlEvent::StartElement { name, .. } => path.push(name.local_name),
+                XmlEvent::EndElement { .. } => {
+                    path.pop();
+                }
+                XmlEvent::Characters(text) => {
+                    elements.insert(path.join("/"), text);
+                }
+                _ => {}
+            }
+        }
+        elements
+    }
+
+    /// Map the JUL level with the conditions of the rendered VRL level expression
+    fn map_jul_level(vector_config: &str, jul_level: &str) -> String {
+        let level_expression = vector_config
+            .lines()
+            .find_map(|line| line.trim_start().strip_prefix(".level = if "))
+            .expect("level expression not found in the Vector config");
+        let condition = Regex::new(r#"level == "(\w+)" \{ "(\w+)" \}"#).unwrap();
+        let fallback = Regex::new(r#"else \{ "(\w+)" \}$"#).unwrap();
+
+        let levels = condition
+            .captures_iter(level_expression)
+            .map(|captures| (captures[1].to_string(), captures[2].to_string()))
+            .collect::<BTreeMap<_, _>>();
+
+        levels.get(jul_level).cloned().unwrap_or_else(|| {
+            fallback
+                .captures(level_expression)
+                .expect("fallback level not found")[1]
+                .to_string()
+        })
+    }
+
+    /// Extract the fields of the given event like the `processed_files_jul` transform, events
+    /// which are not records are aborted
+    ///
+    /// VRL parses timestamps with chrono as well, so the format of the transform is used as is.
+    fn process_jul_event(vector_config: &str, event: &str) -> Option<JulEvent> {
+        if !event.starts_with("<record>") {
+            return None;
+        }
+        let record = parse_xml(event);
+        let field = |name: &str| record.get(&format!("record/{name}")).cloned();
+
+        let timestamp_format =
+            vector_config_value(vector_config, "timestamp, err = parse_timestamp(");
+        let timestamp = NaiveDateTime::parse_from_str(
+            &field("date").expect("date not found"),
+            timestamp_format,
+        )
+        .expect("timestamp is parsable");
+        let mut message = field("message").unwrap_or_default();
+        if let Some(exception) = field("exception/message") {
+            message = format!("{message}\n{exception}");
+        }
+
+        Some(JulEvent {
+            timestamp,
+            logger: field("logger").unwrap_or_default(),
+            level: map_jul_level(vector_config, &field("level").expect("level not found")),
+            thread: field("thread").unwrap_or_default(),
+            message,
+        })
+    }
+
+    #[test]
+    fn test_java_util_logging_levels() {
+        assert_eq!("FINEST", to_java_util_logging_level(LogLevel::TRACE));
+        assert_eq!("FINE", to_java_util_logging_level(LogLevel::DEBUG));
+        assert_eq!("INFO", to_java_util_logging_level(LogLevel::INFO));
+        assert_eq!("WARNING", to_java_util_logging_level(LogLevel::WARN));
+        assert_eq!("SEVERE", to_java_util_logging_level(LogLevel::ERROR));
+        assert_eq!("SEVERE", to_java_util_logging_level(LogLevel::FATAL));
+        assert_eq!("OFF", to_java_util_logging_level(LogLevel::NONE));
+    }
+
+    #[test]
+    fn test_java_logging_config() {
+        let config = AutomaticContainerLogConfig {
+            loggers: BTreeMap::from([
+                (
+                    AutomaticContainerLogConfig::ROOT_LOGGER.to_owned(),
+                    LoggerConfig {
+                        level: LogLevel::WARN,
+                    },
+                ),
+                (
+                    "org.eclipse.edc".to_owned(),
+                    LoggerConfig {
+                        level: LogLevel::DEBUG,
+                    },
+                ),
+            ]),
+            console: Some(AppenderConfig {
+                level: Some(LogLevel::ERROR),
+            }),
+            file: Some(AppenderConfig {
+                level: Some(LogLevel::TRACE),
+            }),
+        };
+
+        let logging_config = create_java_logging_config(
+            "/stackable/log/edc",
+            "edc",
+            10,
+            CONSOLE_CONVERSION_PATTERN,
+            &config,
+        );
+        let lines = logging_config.lines().collect::<Vec<_>>();
+
+        assert!(lines.contains(&".level=WARNING"));
+        assert!(lines.contains(&"org.eclipse.edc.level=FINE"));
+        assert!(!lines.iter().any(|line| line.starts_with("ROOT.level")));
+        assert!(lines.contains(&"java.util.logging.ConsoleHandler.level=SEVERE"));
+        assert!(lines.contains(&"java.util.logging.FileHandler.level=FINEST"));
+        assert!(lines
+            .contains(&"java.util.logging.FileHandler.pattern=/stackable/log/edc/edc.%g.logger"));
+        assert!(lines.contains(&"java.util.logging.FileHandler.limit=5242880"));
+        assert!(lines.contains(&"java.util.logging.FileHandler.count=2"));
+    }
+
+    #[test]
+    fn test_java_logging_config_uses_structured_file_output() {
+        let logging_config = create_java_logging_config(
+            "/stackable/log/edc",
+            "edc",
+            10,
+            CONSOLE_CONVERSION_PATTERN,
+            &AutomaticContainerLogConfig::default(),
+        );
+
+        assert!(logging_config
+            .lines()
+            .any(|line| line
+                == "java.util.logging.FileHandler.formatter=java.util.logging.XMLFormatter"));
+    }
+
+    #[test]
+    fn test_java_util_logging_levels_are_mapped_back() {
+        // Vector maps the levels written by the connector back with JAVA_UTIL_LOGGING_LEVELS
+        for level in [
+            LogLevel::TRACE,
+            LogLevel::DEBUG,
+            LogLevel::INFO,
+            LogLevel::WARN,
+            LogLevel::ERROR,
+        ] {
+            let jul_level = to_java_util_logging_level(level);
+            let level_name = serde_json::to_value(level).unwrap();
+            assert!(
+                JAVA_UTIL_LOGGING_LEVELS
+                    .iter()
+                    .any(|mapping| *mapping == (jul_level, level_name.as_str().unwrap())),
+                "{jul_level}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_jul_records() {
+        let vector_config = create_vector_config(
+            &edc_cluster("").server_rolegroup_ref("default"),
+            "vector-aggregator:6000",
+            None,
+        );
+
+        let events = multiline_events(&vector_config, JUL_LOG_FILE)
+            .iter()
+            .filter_map(|event| process_jul_event(&vector_config, event))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            vec![
+                JulEvent {
+                    timestamp: "2024-06-03T09:22:39.123456".parse().unwrap(),
+                    logger: "org.eclipse.edc.boot.system.ExtensionLoader".to_string(),
+                    level: "WARN".to_string(),
+                    thread: "1".to_string(),
+                    message: "Config value: no setting found for 'edc.hostname'".to_string(),
+                },
+                // ISO_INSTANT omits the fractional seconds of a full second
+                JulEvent {
+                    timestamp: "2024-06-03T09:22:40".parse().unwrap(),
+                    logger: "org.eclipse.edc.connector.dataplane".to_string(),
+                    level: "ERROR".to_string(),
+                    thread: "27".to_string(),
+                    message: "Transfer failed\njava.io.IOException: Connection reset".to_string(),
+                },
+                JulEvent {
+                    timestamp: "2024-06-03T09:22:41.500".parse().unwrap(),
+                    logger: "org.eclipse.edc.boot".to_string(),
+                    level: "TRACE".to_string(),
+                    thread: "1".to_string(),
+                    message: "Custom level".to_string(),
+                },
+            ],
+            events
+        );
+    }
+
+    #[test]
+    fn test_vector_config() {
+        let vector_config = create_vector_config(
+            &edc_cluster("").server_rolegroup_ref("default"),
+            "vector-aggregator:6000",
+            None,
+        );
+
+        assert_eq!(include_str!("../test/snapshots/vector.toml"), vector_config);
+    }
+}
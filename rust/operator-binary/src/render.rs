@@ -0,0 +1,417 @@
+//! Offline rendering of the resources which the operator generates for an EDCCluster, e.g. to
+//! review them in a GitOps workflow or to debug the operator without a Kubernetes cluster
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use product_config::ProductConfigManager;
+use serde::{de::DeserializeOwned, Serialize};
+use snafu::{OptionExt, ResultExt, Snafu};
+use stackable_operator::{
+    cli::ProductConfigPath,
+    commons::{
+        rbac::build_rbac_resources,
+        s3::{S3Bucket, S3BucketDef, S3Connection, S3ConnectionDef, S3ConnectionSpec},
+    },
+    kube::ResourceExt,
+};
+
+use crate::{
+    autoscaling::{self, build_rolegroup_hpa},
+    controller::{
+        self, build_connector_rolegroup_config_map, build_rolegroup_service,
+        build_server_role_service, build_server_rolegroup_statefulset, compute_config_hash,
+        validated_product_config, DOCKER_IMAGE_BASE_NAME,
+    },
+    crd::{EDCCluster, EDCRole, APP_NAME},
+    network_policy::{self, build_network_policy},
+    pdb::{self, build_pdb},
+    validation,
+};
+
+/// Owner references require the uid of the EDCCluster, which is only assigned by Kubernetes
+const PLACEHOLDER_UID: &str = "00000000-0000-0000-0000-000000000000";
+
+#[derive(Snafu, Debug)]
+pub enum Error {
+    #[snafu(display("failed to read [{}]", path.display()))]
+    ReadFile {
+        source: std::io::Error,
+        path: PathBuf,
+    },
+    #[snafu(display("failed to parse [{}]", path.display()))]
+    ParseFile {
+        source: serde_yaml::Error,
+        path: PathBuf,
+    },
+    #[snafu(display("the EDCCluster is invalid at [{}]", source.field()))]
+    InvalidCluster { source: validation::Error },
+    #[snafu(display("the referenced S3Bucket [{name}] is not given"))]
+    MissingS3Bucket { name: String },
+    #[snafu(display("the referenced S3Connection [{name}] is not given"))]
+    MissingS3Connection { name: String },
+    #[snafu(display("failed to build the resources"))]
+    BuildResources { source: controller::Error },
+    #[snafu(display("failed to build the ServiceAccount"))]
+    BuildRbacResources {
+        source: stackable_operator::commons::rbac::Error,
+    },
+    #[snafu(display("failed to build the PodDisruptionBudget"))]
+    BuildPdb { source: pdb::Error },
+    #[snafu(display("failed to build the NetworkPolicy"))]
+    BuildNetworkPolicy { source: network_policy::Error },
+    #[snafu(display(
+        "failed to build the HorizontalPodAutoscaler of the rolegroup [{rolegroup}]"
+    ))]
+    BuildHpa {
+        source: autoscaling::Error,
+        rolegroup: String,
+    },
+    #[snafu(display("failed to resolve the config of the rolegroup [{rolegroup}]"))]
+    ResolveConfig {
+        source: crate::crd::Error,
+        rolegroup: String,
+    },
+    #[snafu(display("failed to serialize the resources"))]
+    SerializeResource { source: serde_yaml::Error },
+}
+
+type Result<T, E = Error> = std::result::Result<T, E>;
+
+#[derive(clap::Args, Debug)]
+pub struct RenderArgs {
+    /// File with the EDCCluster
+    #[arg(value_name = "FILE")]
+    pub edc_cluster: PathBuf,
+
+    /// File with an S3Bucket referenced by the EDCCluster
+    #[arg(long = "s3-bucket", value_name = "FILE")]
+    pub s3_buckets: Vec<PathBuf>,
+
+    /// File with an S3Connection referenced by the EDCCluster or the S3Bucket
+    #[arg(long = "s3-connection", value_name = "FILE")]
+    pub s3_connections: Vec<PathBuf>,
+
+    /// Namespace of the EDCCluster if it is not set in the file
+    #[arg(long, default_value = "default")]
+    pub namespace: String,
+
+    /// Address of the Vector aggregator, which is required if the Vector agent is enabled
+    #[arg(long)]
+    pub vector_aggregator_address: Option<String>,
+
+    /// Namespace of the operator, which is allowed to call the connectors in the NetworkPolicy
+    #[arg(long)]
+    pub operator_namespace: Option<String>,
+
+    /// Path to the product config, the default locations are searched if it is not given
+    // Same as in `ProductOperatorRun`, where the empty default selects the default locations
+    #[arg(long, short = 'p', value_name = "FILE", default_value = "", env)]
+    pub product_config: ProductConfigPath,
+}
+
+/// Render the Services, PodDisruptionBudgets, NetworkPolicies, ConfigMaps, StatefulSets and
+/// HorizontalPodAutoscalers of the EDCCluster given in the arguments as YAML documents
+///
+/// The config hashes in the StatefulSets differ from the ones in a Kubernetes cluster, because
+/// the referenced Secrets are not taken into account.
+pub fn render(args: &RenderArgs, product_config: &ProductConfigManager) -> Result<String> {
+    let mut edc = read_yaml::<EDCCluster>(&args.edc_cluster)?;
+    edc.metadata
+        .namespace
+        .get_or_insert_with(|| args.namespace.clone());
+    edc.metadata
+        .uid
+        .get_or_insert_with(|| PLACEHOLDER_UID.to_string());
+
+    let s3_buckets = args
+        .s3_buckets
+        .iter()
+        .map(|path| read_yaml::<S3Bucket>(path))
+        .collect::<Result<Vec<_>>>()?;
+    let s3_connections = args
+        .s3_connections
+        .iter()
+        .map(|path| read_yaml::<S3Connection>(path))
+        .collect::<Result<Vec<_>>>()?;
+
+    let s3_connection = resolve_s3_connection(&edc, &s3_buckets, &s3_connections)?;
+
+    render_resources(
+        &edc,
+        s3_connection.as_ref(),
+        args.vector_aggregator_address.as_deref(),
+        args.operator_namespace.as_deref(),
+        product_config,
+    )
+}
+
+fn render_resources(
+    edc: &EDCCluster,
+    s3_connection: Option<&S3ConnectionSpec>,
+    vector_aggregator_address: Option<&str>,
+    operator_namespace: Option<&str>,
+    product_config: &ProductConfigManager,
+) -> Result<String> {
+    if let Some(error) = validation::validate(edc).into_iter().next() {
+        return Err(error).context(InvalidClusterSnafu);
+    }
+
+    let resolved_product_image = edc
+        .spec
+        .image
+        .resolve(DOCKER_IMAGE_BASE_NAME, crate::built_info::PKG_VERSION);
+    let validated_config =
+        validated_product_config(edc, &resolved_product_image.product_version, product_config)
+            .context(BuildResourcesSnafu)?;
+    let server_config = validated_config
+        .get(&EDCRole::Connector.to_string())
+        .cloned()
+        .unwrap_or_default();
+
+    // The labels are only needed to name the ServiceAccount
+    let (rbac_sa, _) =
+        build_rbac_resources(edc, APP_NAME, Default::default()).context(BuildRbacResourcesSnafu)?;
+
+    let mut documents = vec![to_yaml(
+        &build_server_role_service(edc, &resolved_product_image).context(BuildResourcesSnafu)?,
+    )?];
+
+    if let Some(pdb) = build_pdb(edc, &EDCRole::Connector).context(BuildPdbSnafu)? {
+        documents.push(to_yaml(&pdb)?);
+    }
+    if let Some(network_policy) = build_network_policy(
+        edc,
+        &resolved_product_image,
+        &server_config,
+        s3_connection,
+        vector_aggregator_address,
+        operator_namespace,
+    )
+    .context(BuildNetworkPolicySnafu)?
+    {
+        documents.push(to_yaml(&network_policy)?);
+    }
+
+    // Render the rolegroups in a stable order
+    for (rolegroup_name, rolegroup_config) in server_config.iter().collect::<BTreeMap<_, _>>() {
+        let rolegroup = edc.server_rolegroup_ref(rolegroup_name);
+        let config = edc
+            .merged_config(&EDCRole::Connector, rolegroup_name)
+            .context(ResolveConfigSnafu {
+                rolegroup: rolegroup_name,
+            })?;
+
+        let rg_service = build_rolegroup_service(edc, &resolved_product_image, &rolegroup)
+            .context(BuildResourcesSnafu)?;
+        let rg_configmap = build_connector_rolegroup_config_map(
+            edc,
+            &resolved_product_image,
+            &rolegroup,
+            rolegroup_config,
+            &config,
+            s3_connection,
+            vector_aggregator_address,
+        )
+        .context(BuildResourcesSnafu)?;
+        let rg_statefulset = build_server_rolegroup_statefulset(
+            edc,
+            &resolved_product_image,
+            &rolegroup,
+            rolegroup_config,
+            &config,
+            s3_connection,
+            &rbac_sa.name_any(),
+            &compute_config_hash(&rg_configmap, &[]),
+        )
+        .context(BuildResourcesSnafu)?;
+
+        documents.push(to_yaml(&rg_service)?);
+        documents.push(to_yaml(&rg_configmap)?);
+        documents.push(to_yaml(&rg_statefulset)?);
+
+        if let Some(autoscaling) = config
+            .autoscaling
+            .as_ref()
+            .filter(|_| !edc.spec.cluster_operation.stopped)
+        {
+            let rg_hpa = build_rolegroup_hpa(edc, &resolved_product_image, &rolegroup, autoscaling)
+                .context(BuildHpaSnafu {
+                    rolegroup: rolegroup_name,
+                })?;
+            documents.push(to_yaml(&rg_hpa)?);
+        }
+    }
+
+    Ok(documents
+        .into_iter()
+        .map(|document| format!("---\n{document}"))
+        .collect())
+}
+
+/// Resolve the S3 connection of the EDCCluster from the given S3Buckets and S3Connections in the
+/// same way as the reconciler resolves it from the Kubernetes cluster
+pub fn resolve_s3_connection(
+    edc: &EDCCluster,
+    s3_buckets: &[S3Bucket],
+    s3_connections: &[S3Connection],
+) -> Result<Option<S3ConnectionSpec>> {
+    let connection = match &edc.spec.cluster_config.ionos.s3 {
+        S3BucketDef::Inline(bucket) => bucket.connection.as_ref(),
+        S3BucketDef::Reference(bucket_name) => s3_buckets
+            .iter()
+            .find(|bucket| bucket.name_any() == *bucket_name)
+            .context(MissingS3BucketSnafu { name: bucket_name })?
+            .spec
+            .connection
+            .as_ref(),
+    };
+
+    match connection {
+        None => Ok(None),
+        Some(S3ConnectionDef::Inline(connection)) => Ok(Some(connection.clone())),
+        Some(S3ConnectionDef::Reference(connection_name)) => s3_connections
+            .iter()
+            .find(|connection| connection.name_any() == *connection_name)
+            .map(|connection| Some(connection.spec.clone()))
+            .context(MissingS3ConnectionSnafu {
+                name: connection_name,
+            }),
+    }
+}
+
+fn read_yaml<T: DeserializeOwned>(path: &Path) -> Result<T> {
+    let content = fs::read_to_string(path).context(ReadFileSnafu { path })?;
+    serde_yaml::from_str(&content).context(ParseFileSnafu { path })
+}
+
+fn to_yaml(resource: &impl Serialize) -> Result<String> {
+    serde_yaml::to_string(resource).context(SerializeResourceSnafu)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+
+    use clap::Parser;
+    use stackable_operator::cli::ProductOperatorRun;
+
+    use super::*;
+    use crate::test_fixtures::edc_cluster;
+
+    #[derive(Parser)]
+    struct RenderCli {
+        #[command(flatten)]
+        args: RenderArgs,
+    }
+
+    #[derive(Parser)]
+    struct OperatorRunCli {
+        #[command(flatten)]
+        args: ProductOperatorRun,
+    }
+
+    #[test]
+    fn test_default_product_config_path() {
+        let render_args = RenderCli::parse_from(["render", "edc.yaml"]).args;
+        let operator_run = OperatorRunCli::parse_from(["run"]).args;
+
+        // Without a path, both commands search the default locations of the product config
+        assert_eq!(operator_run.product_config, render_args.product_config);
+    }
+
+    #[test]
+    fn test_render_resources() {
+        let edc = edc_cluster(
+            "
+            roleGroups:
+              default:
+                replicas: 1
+              secondary:
+                config:
+                  autoscaling:
+                    maxReplicas: 3
+            ",
+        );
+        let s3_bucket: S3Bucket = serde_yaml::from_str(
+            r#"
+            apiVersion: s3.stackable.tech/v1alpha1
+            kind: S3Bucket
+            metadata:
+              name: source-bucket
+              namespace: default
+            spec:
+              bucketName: source
+              connection:
+                reference: ionos-s3
+            "#,
+        )
+        .expect("invalid test input");
+        let s3_connection: S3Connection = serde_yaml::from_str(
+            r#"
+            apiVersion: s3.stackable.tech/v1alpha1
+            kind: S3Connection
+            metadata:
+              name: ionos-s3
+              namespace: default
+            spec:
+              host: s3-eu-central-1.ionoscloud.com
+            "#,
+        )
+        .expect("invalid test input");
+        let product_config = ProductConfigManager::from_yaml_file(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../../deploy/config-spec/properties.yaml"
+        ))
+        .expect("invalid product config");
+
+        let s3_connection = resolve_s3_connection(&edc, &[s3_bucket], &[s3_connection])
+            .expect("failed to resolve the S3 connection");
+        assert_eq!(
+            Some("s3-eu-central-1.ionoscloud.com"),
+            s3_connection
+                .as_ref()
+                .and_then(|connection| connection.host.as_deref())
+        );
+
+        let rendered = render_resources(&edc, s3_connection.as_ref(), None, None, &product_config)
+            .expect("failed to render the resources");
+        let kinds = serde_yaml::Deserializer::from_str(&rendered)
+            .map(|document| {
+                let value = serde_yaml::Value::deserialize(document).unwrap();
+                format!(
+                    "{}/{}",
+                    value["kind"].as_str().unwrap(),
+                    value["metadata"]["name"].as_str().unwrap()
+                )
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            vec![
+                "Service/provider",
+                "PodDisruptionBudget/provider-server",
+                "Service/provider-server-default",
+                "ConfigMap/provider-server-default",
+                "StatefulSet/provider-server-default",
+                "Service/provider-server-secondary",
+                "ConfigMap/provider-server-secondary",
+                "StatefulSet/provider-server-secondary",
+                "HorizontalPodAutoscaler/provider-server-secondary",
+            ],
+            kinds
+        );
+    }
+
+    #[test]
+    fn test_missing_s3_bucket() {
+        let edc = edc_cluster("");
+
+        assert!(matches!(
+            resolve_s3_connection(&edc, &[], &[]),
+            Err(Error::MissingS3Bucket { name }) if name == "source-bucket"
+        ));
+    }
+}
@@ -0,0 +1,85 @@
+//! Security contexts of the connector Pods, which comply with the restricted Pod Security Standard
+use stackable_operator::{
+    builder::pod::{container::ContainerBuilder, security::PodSecurityContextBuilder, PodBuilder},
+    k8s_openapi::api::core::v1::{
+        Capabilities, Container, EmptyDirVolumeSource, SeccompProfile, SecurityContext, Volume,
+        VolumeMount,
+    },
+};
+
+use crate::crd::ConnectorConfig;
+
+/// The root filesystem is read-only, so temporary files, e.g. of the JVM, are written to an
+/// emptyDir
+const TMP_DIR: &str = "/tmp";
+const TMP_DIR_NAME: &str = "tmp";
+/// Directory in which Vector keeps its state, see `data_dir` in the Vector config
+const VECTOR_DATA_DIR: &str = "/stackable/vector/var";
+const VECTOR_DATA_DIR_NAME: &str = "vector-data";
+const SECCOMP_PROFILE_RUNTIME_DEFAULT: &str = "RuntimeDefault";
+
+/// Run the Pod as the configured non-root user and harden the connector container
+pub fn add_security_context(
+    merged_config: &ConnectorConfig,
+    pod_builder: &mut PodBuilder,
+    container_builder: &mut ContainerBuilder,
+) {
+    let security_context = &merged_config.security_context;
+
+    pod_builder
+        .security_context(
+            PodSecurityContextBuilder::new()
+                .run_as_user(security_context.run_as_user)
+                .run_as_group(security_context.run_as_group)
+                .fs_group(security_context.fs_group)
+                .run_as_non_root()
+                .seccomp_profile_type(SECCOMP_PROFILE_RUNTIME_DEFAULT)
+                .build(),
+        )
+        .add_volume(empty_dir_volume(TMP_DIR_NAME));
+
+    container_builder
+        .add_volume_mount(TMP_DIR_NAME, TMP_DIR)
+        .security_context(container_security_context());
+}
+
+/// Harden the Vector sidecar in the same way as the connector container
+pub fn add_vector_security_context(pod_builder: &mut PodBuilder, container: &mut Container) {
+    pod_builder.add_volume(empty_dir_volume(VECTOR_DATA_DIR_NAME));
+
+    container
+        .volume_mounts
+        .get_or_insert_with(Vec::new)
+        .push(VolumeMount {
+            name: VECTOR_DATA_DIR_NAME.to_string(),
+            mount_path: VECTOR_DATA_DIR.to_string(),
+            ..VolumeMount::default()
+        });
+    container.security_context = Some(container_security_context());
+}
+
+/// Container security context with a read-only root filesystem and without privileges
+fn container_security_context() -> SecurityContext {
+    SecurityContext {
+        allow_privilege_escalation: Some(false),
+        capabilities: Some(Capabilities {
+            add: None,
+            drop: Some(vec!["ALL".to_string()]),
+        }),
+        read_only_root_filesystem: Some(true),
+        run_as_non_root: Some(true),
+        seccomp_profile: Some(SeccompProfile {
+            type_: SECCOMP_PROFILE_RUNTIME_DEFAULT.to_string(),
+            localhost_profile: None,
+        }),
+        ..SecurityContext::default()
+    }
+}
+
+fn empty_dir_volume(name: &str) -> Volume {
+    Volume {
+        name: name.to_string(),
+        empty_dir: Some(EmptyDirVolumeSource::default()),
+        ..Volume::default()
+    }
+}
@@ -0,0 +1,69 @@
+//! EDCCluster fixtures shared by the unit tests
+use serde_yaml::Value;
+
+use crate::crd::EDCCluster;
+
+/// Valid EDCCluster without the `connectors` role, which the fixtures are derived from
+const EDC_CLUSTER: &str = "
+apiVersion: edc.stackable.tech/v1alpha1
+kind: EDCCluster
+metadata:
+  name: provider
+  namespace: default
+  uid: 42d1c6a1-3f1a-4e54-9a33-22a3c5a7b1c1
+spec:
+  image:
+    productVersion: 0.1.2
+  clusterConfig:
+    certSecret: connector-cert
+    ionos:
+      tokenSecret: ionos-token
+      s3:
+        reference: source-bucket
+";
+
+/// The EDCCluster `default/provider` with the given `connectors` role, e.g.
+/// `"roleGroups: {default: {replicas: 1}}"`, or without it if the YAML is empty
+pub fn edc_cluster(connectors: &str) -> EDCCluster {
+    edc_cluster_with_spec("", connectors)
+}
+
+/// Like [`edc_cluster`], but the given YAML is additionally merged into the spec, e.g.
+/// `"clusterConfig: {metrics: {enabled: true}}"`
+pub fn edc_cluster_with_spec(spec: &str, connectors: &str) -> EDCCluster {
+    serde_yaml::from_value(edc_cluster_manifest(spec, connectors)).expect("invalid test input")
+}
+
+/// The manifest of [`edc_cluster_with_spec`], e.g. to send it in an admission review
+///
+/// Mappings are merged recursively and keys with a `null` value are removed from the spec.
+pub fn edc_cluster_manifest(spec: &str, connectors: &str) -> Value {
+    let mut manifest: Value = serde_yaml::from_str(EDC_CLUSTER).expect("invalid fixture");
+    merge(
+        &mut manifest["spec"],
+        serde_yaml::from_str(spec).expect("invalid test input"),
+    );
+    let connectors: Value = serde_yaml::from_str(connectors).expect("invalid test input");
+    if !connectors.is_null() {
+        manifest["spec"]["connectors"] = connectors;
+    }
+    manifest
+}
+
+fn merge(base: &mut Value, overlay: Value) {
+    match (base, overlay) {
+        (Value::Mapping(base), Value::Mapping(overlay)) => {
+            for (key, value) in overlay {
+                if value.is_null() {
+                    base.remove(&key);
+                } else if let Some(base_value) = base.get_mut(&key) {
+                    merge(base_value, value);
+                } else {
+                    base.insert(key, value);
+                }
+            }
+        }
+        (_, Value::Null) => {}
+        (base, overlay) => *base = overlay,
+    }
+}
@@ -0,0 +1,468 @@
+//! Validation of the EDCCluster spec, which is done by the reconciler and by the admission webhook
+//! before the EDCCluster is stored
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde_json::Value;
+use snafu::Snafu;
+
+use crate::{
+    controller::{self, construct_heap_jvm_args},
+    crd::{
+        CurrentlySupportedListenerClasses, EDCCluster, EDCRole, CONFIG_PROPERTIES,
+        EDC_DATASOURCE_DEFAULT_URL, WEB_CONTEXTS,
+    },
+};
+
+#[derive(Snafu, Debug)]
+pub enum Error {
+    #[snafu(display("the connectors role is required"))]
+    NoConnectorsRole,
+    #[snafu(display("the connectors role requires at least one rolegroup"))]
+    NoRoleGroups,
+    #[snafu(display("the name of the Secret with the connector certificate must not be empty"))]
+    EmptyCertSecret,
+    #[snafu(display("the name of the Secret with the IONOS token must not be empty"))]
+    EmptyTokenSecret,
+    #[snafu(display(
+        "unknown listener class [{listener_class}], supported are cluster-internal, external-unstable and external-stable"
+    ))]
+    UnknownListenerClass { listener_class: String },
+    #[snafu(display(
+        "the management sources of the NetworkPolicy must select namespaces or Pods, IP blocks are not allowed"
+    ))]
+    ManagementSourceIpBlock,
+    #[snafu(display(
+        "minReplicas [{min_replicas}] must be at least 1 and must not exceed maxReplicas [{max_replicas}]"
+    ))]
+    InvalidAutoscalingReplicas {
+        field: String,
+        min_replicas: u16,
+        max_replicas: u16,
+    },
+    #[snafu(display("the connectors must not run as root, runAsUser must not be 0"))]
+    RootUser { field: String },
+    #[snafu(display(
+        "the maximum heap [{max}] does not fit into the memory limit [{memory_limit}] besides the non-heap memory of the JVM, the connector would be OOMKilled"
+    ))]
+    HeapExceedsMemoryLimit {
+        field: String,
+        max: String,
+        memory_limit: String,
+    },
+    #[snafu(display(
+        "the initial heap [{min}] must not exceed the maximum heap of {max_mebibytes}Mi, the JVM would not start"
+    ))]
+    HeapMinAboveMax {
+        field: String,
+        min: String,
+        max_mebibytes: u32,
+    },
+    #[snafu(display(
+        "the port [{property}] must not be overridden, the container ports, Services, probes and the NetworkPolicy are built with the default port"
+    ))]
+    PortOverride { field: String, property: String },
+}
+
+impl Error {
+    /// Path of the invalid field in the EDCCluster
+    pub fn field(&self) -> String {
+        match self {
+            Error::NoConnectorsRole => "spec.connectors".to_string(),
+            Error::NoRoleGroups => "spec.connectors.roleGroups".to_string(),
+            Error::EmptyCertSecret => "spec.clusterConfig.certSecret".to_string(),
+            Error::EmptyTokenSecret => "spec.clusterConfig.ionos.tokenSecret".to_string(),
+            Error::UnknownListenerClass { .. } => "spec.clusterConfig.listenerClass".to_string(),
+            Error::ManagementSourceIpBlock => {
+                "spec.clusterConfig.networkPolicy.managementSources".to_string()
+            }
+            Error::InvalidAutoscalingReplicas { field, .. }
+            | Error::RootUser { field }
+            | Error::HeapExceedsMemoryLimit { field, .. }
+            | Error::HeapMinAboveMax { field, .. }
+            | Error::PortOverride { field, .. } => field.clone(),
+        }
+    }
+}
+
+/// Validate the EDCCluster, all violations are returned
+pub fn validate(edc: &EDCCluster) -> Vec<Error> {
+    let mut errors = Vec::new();
+    let cluster_config = &edc.spec.cluster_config;
+
+    match &edc.spec.connectors {
+        None => errors.push(Error::NoConnectorsRole),
+        Some(role) if role.role_groups.is_empty() => errors.push(Error::NoRoleGroups),
+        Some(role) => {
+            let configs = [("spec.connectors.config".to_string(), &role.config.config)]
+                .into_iter()
+                // Report the rolegroups in a stable order
+                .chain(
+                    role.role_groups
+                        .iter()
+                        .collect::<BTreeMap<_, _>>()
+                        .into_iter()
+                        .map(|(rolegroup_name, rolegroup)| {
+                            (
+                                format!("spec.connectors.roleGroups.{rolegroup_name}.config"),
+                                &rolegroup.config.config,
+                            )
+                        }),
+                );
+            for (path, config) in configs {
+                if let Some(autoscaling) = &config.autoscaling {
+                    if autoscaling.min_replicas == 0
+                        || autoscaling.min_replicas > autoscaling.max_replicas
+                    {
+                        errors.push(Error::InvalidAutoscalingReplicas {
+                            field: format!("{path}.autoscaling.minReplicas"),
+                            min_replicas: autoscaling.min_replicas,
+                            max_replicas: autoscaling.max_replicas,
+                        });
+                    }
+                }
+                if config.security_context.run_as_user == Some(0) {
+                    errors.push(Error::RootUser {
+                        field: format!("{path}.securityContext.runAsUser"),
+                    });
+                }
+            }
+
+            let config_overrides = [(
+                "spec.connectors.configOverrides".to_string(),
+                &role.config.config_overrides,
+            )]
+            .into_iter()
+            .chain(
+                role.role_groups
+                    .iter()
+                    .collect::<BTreeMap<_, _>>()
+                    .into_iter()
+                    .map(|(rolegroup_name, rolegroup)| {
+                        (
+                            format!("spec.connectors.roleGroups.{rolegroup_name}.configOverrides"),
+                            &rolegroup.config.config_overrides,
+                        )
+                    }),
+            );
+            for (path, config_overrides) in config_overrides {
+                let Some(properties) = config_overrides.get(CONFIG_PROPERTIES) else {
+                    continue;
+                };
+                for web_context in WEB_CONTEXTS {
+                    if properties.contains_key(web_context.port_property) {
+                        errors.push(Error::PortOverride {
+                            field: format!("{path}.{CONFIG_PROPERTIES}"),
+                            property: web_context.port_property.to_string(),
+                        });
+                    }
+                }
+            }
+
+            // The heap depends on the memory limit and the runtimeLimits, which can be set on
+            // different levels, so it is checked with the merged config of every rolegroup
+            for rolegroup_name in role.role_groups.keys().collect::<BTreeSet<_>>() {
+                let Ok(config) = edc.merged_config(&EDCRole::Connector, rolegroup_name) else {
+                    continue;
+                };
+                let path = format!(
+                    "spec.connectors.roleGroups.{rolegroup_name}.config.resources.memory.runtimeLimits"
+                );
+                match construct_heap_jvm_args(&config) {
+                    Err(controller::Error::HeapExceedsMemoryLimit { max, memory_limit }) => errors
+                        .push(Error::HeapExceedsMemoryLimit {
+                            field: format!("{path}.max"),
+                            max: max.0,
+                            memory_limit: memory_limit.0,
+                        }),
+                    Err(controller::Error::HeapMinAboveMax { min, max_mebibytes }) => {
+                        errors.push(Error::HeapMinAboveMax {
+                            field: format!("{path}.min"),
+                            min: min.0,
+                            max_mebibytes,
+                        })
+                    }
+                    // The other errors are reported by the reconciliation
+                    _ => {}
+                }
+            }
+        }
+    }
+    if cluster_config.cert_secret.trim().is_empty() {
+        errors.push(Error::EmptyCertSecret);
+    }
+    if cluster_config.ionos.token_secret.trim().is_empty() {
+        errors.push(Error::EmptyTokenSecret);
+    }
+    if cluster_config
+        .network_policy
+        .management_sources
+        .iter()
+        .any(|source| source.ip_block.is_some())
+    {
+        errors.push(Error::ManagementSourceIpBlock);
+    }
+
+    errors
+}
+
+/// Validate the listener class of the not yet deserialized EDCCluster, an unknown listener class
+/// would otherwise only be reported as a deserialization error
+pub fn validate_listener_class(edc: &Value) -> Result<(), Error> {
+    let Some(listener_class) = edc.pointer("/spec/clusterConfig/listenerClass") else {
+        return Ok(());
+    };
+
+    match serde_json::from_value::<CurrentlySupportedListenerClasses>(listener_class.clone()) {
+        Ok(_) => Ok(()),
+        Err(_) => UnknownListenerClassSnafu {
+            listener_class: listener_class
+                .as_str()
+                .map(ToString::to_string)
+                .unwrap_or_else(|| listener_class.to_string()),
+        }
+        .fail(),
+    }
+}
+
+/// Warnings about valid but probably unintended settings of the EDCCluster
+pub fn warnings(edc: &EDCCluster) -> Vec<String> {
+    let Some(role) = &edc.spec.connectors else {
+        return Vec::new();
+    };
+
+    role.role_groups
+        .iter()
+        // Report the rolegroups in a stable order
+        .collect::<BTreeMap<_, _>>()
+        .into_iter()
+        .filter(|(_, rolegroup)| rolegroup.replicas.unwrap_or(0) > 1)
+        .filter(|(_, rolegroup)| {
+            [&role.config.config_overrides, &rolegroup.config.config_overrides]
+                .into_iter()
+                .filter_map(|overrides| overrides.get(CONFIG_PROPERTIES))
+                .all(|properties| !properties.contains_key(EDC_DATASOURCE_DEFAULT_URL))
+        })
+        .map(|(rolegroup_name, rolegroup)| {
+            format!(
+                "spec.connectors.roleGroups.{rolegroup_name}.replicas: {replicas} replicas do not share their state because no persistent store is configured with [{EDC_DATASOURCE_DEFAULT_URL}] in the configOverrides, contract negotiations and transfers are only known to the replica which handled them",
+                replicas = rolegroup.replicas.unwrap_or(0)
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::test_fixtures::{edc_cluster, edc_cluster_with_spec};
+
+    #[test]
+    fn test_valid_cluster() {
+        let edc = edc_cluster("roleGroups: {default: {replicas: 1}}");
+
+        assert!(validate(&edc).is_empty());
+        assert!(warnings(&edc).is_empty());
+    }
+
+    #[test]
+    fn test_invalid_cluster() {
+        let edc = edc_cluster_with_spec("clusterConfig: {certSecret: ''}", "");
+
+        let fields = validate(&edc).iter().map(Error::field).collect::<Vec<_>>();
+
+        assert_eq!(
+            vec!["spec.connectors", "spec.clusterConfig.certSecret"],
+            fields
+        );
+    }
+
+    #[test]
+    fn test_management_source_ip_block() {
+        let edc = edc_cluster_with_spec(
+            "
+            clusterConfig:
+              networkPolicy:
+                enabled: true
+                managementSources:
+                  - podSelector:
+                      matchLabels:
+                        app: backend
+                  - ipBlock:
+                      cidr: 10.0.0.0/8
+            ",
+            "roleGroups: {default: {replicas: 1}}",
+        );
+
+        let fields = validate(&edc).iter().map(Error::field).collect::<Vec<_>>();
+
+        assert_eq!(
+            vec!["spec.clusterConfig.networkPolicy.managementSources"],
+            fields
+        );
+    }
+
+    #[test]
+    fn test_invalid_autoscaling_replicas() {
+        let edc = edc_cluster(
+            "
+            config:
+              autoscaling:
+                maxReplicas: 3
+            roleGroups:
+              control:
+                replicas: 1
+              data:
+                config:
+                  autoscaling:
+                    minReplicas: 4
+                    maxReplicas: 2
+            ",
+        );
+
+        let fields = validate(&edc).iter().map(Error::field).collect::<Vec<_>>();
+
+        assert_eq!(
+            vec!["spec.connectors.roleGroups.data.config.autoscaling.minReplicas"],
+            fields
+        );
+    }
+
+    #[test]
+    fn test_root_user() {
+        let edc = edc_cluster(
+            "
+            config:
+              securityContext:
+                runAsUser: 0
+            roleGroups:
+              default:
+                replicas: 1
+            ",
+        );
+
+        let fields = validate(&edc).iter().map(Error::field).collect::<Vec<_>>();
+
+        assert_eq!(
+            vec!["spec.connectors.config.securityContext.runAsUser"],
+            fields
+        );
+    }
+
+    #[test]
+    fn test_port_override() {
+        let edc = edc_cluster(
+            "
+            configOverrides:
+              config.properties:
+                web.http.protocol.path: /dsp
+                web.http.protocol.port: '9292'
+            roleGroups:
+              default:
+                replicas: 1
+                configOverrides:
+                  config.properties:
+                    web.http.port: '9191'
+            ",
+        );
+
+        let errors = validate(&edc);
+
+        assert_eq!(
+            vec![
+                (
+                    "spec.connectors.configOverrides.config.properties".to_string(),
+                    "web.http.protocol.port".to_string()
+                ),
+                (
+                    "spec.connectors.roleGroups.default.configOverrides.config.properties"
+                        .to_string(),
+                    "web.http.port".to_string()
+                ),
+            ],
+            errors
+                .into_iter()
+                .map(|error| match error {
+                    Error::PortOverride { field, property } => (field, property),
+                    error => panic!("unexpected error {error}"),
+                })
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_invalid_heap() {
+        let edc = edc_cluster(
+            "
+            config:
+              resources:
+                memory:
+                  limit: 2Gi
+                  runtimeLimits:
+                    max: 1Gi
+            roleGroups:
+              default:
+                replicas: 1
+              large:
+                config:
+                  resources:
+                    memory:
+                      runtimeLimits:
+                        max: 3Gi
+              small:
+                config:
+                  resources:
+                    memory:
+                      runtimeLimits:
+                        min: 1536Mi
+            ",
+        );
+
+        let fields = validate(&edc).iter().map(Error::field).collect::<Vec<_>>();
+
+        assert_eq!(
+            vec![
+                "spec.connectors.roleGroups.large.config.resources.memory.runtimeLimits.max",
+                "spec.connectors.roleGroups.small.config.resources.memory.runtimeLimits.min",
+            ],
+            fields
+        );
+    }
+
+    #[test]
+    fn test_unknown_listener_class() {
+        assert!(validate_listener_class(&json!({"spec": {"clusterConfig": {}}})).is_ok());
+        assert!(validate_listener_class(&json!({
+            "spec": {"clusterConfig": {"listenerClass": "external-stable"}}
+        }))
+        .is_ok());
+        assert!(matches!(
+            validate_listener_class(&json!({
+                "spec": {"clusterConfig": {"listenerClass": "external"}}
+            })),
+            Err(Error::UnknownListenerClass { listener_class }) if listener_class == "external"
+        ));
+    }
+
+    #[test]
+    fn test_replicas_without_persistence() {
+        let edc = edc_cluster(
+            "
+            roleGroups:
+              persistent:
+                replicas: 2
+                configOverrides:
+                  config.properties:
+                    edc.datasource.default.url: jdbc:postgresql://postgresql:5432/edc
+              volatile:
+                replicas: 2
+            ",
+        );
+
+        let warnings = warnings(&edc);
+
+        assert_eq!(1, warnings.len());
+        assert!(warnings[0].starts_with("spec.connectors.roleGroups.volatile.replicas: 2 replicas"));
+    }
+}
@@ -0,0 +1,441 @@
+//! HTTPS server for the webhooks of the operator: the admission webhook validates EDCClusters
+//! before they are stored and the conversion webhook converts them between their versions
+use std::{
+    convert::Infallible,
+    fs::File,
+    io::BufReader,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use http_body_util::{BodyExt, Full};
+use hyper::{
+    body::{Bytes, Incoming},
+    header::CONTENT_TYPE,
+    server::conn::http1,
+    service::service_fn,
+    Method, Request, Response, StatusCode,
+};
+use hyper_util::rt::TokioIo;
+use snafu::{OptionExt, ResultExt, Snafu};
+use stackable_operator::{
+    client::Client,
+    k8s_openapi::{
+        apiextensions_apiserver::pkg::apis::apiextensions::v1::{
+            CustomResourceConversion, CustomResourceDefinition, ServiceReference,
+            WebhookClientConfig, WebhookConversion,
+        },
+        ByteString,
+    },
+    kube::{
+        api::{Patch, PatchParams},
+        core::{
+            admission::{AdmissionRequest, AdmissionResponse, AdmissionReview},
+            conversion::{ConversionRequest, ConversionResponse, ConversionReview},
+            DynamicObject, Status,
+        },
+        Api, CustomResourceExt,
+    },
+};
+use tokio::net::TcpListener;
+use tokio_rustls::{rustls::ServerConfig, TlsAcceptor};
+
+use crate::{
+    crd::{v1alpha2, EDCCluster},
+    validation,
+};
+
+/// File names of the certificate and the private key in the TLS directory, as in Secrets of the
+/// type `kubernetes.io/tls`
+const TLS_CERT_FILE: &str = "tls.crt";
+const TLS_KEY_FILE: &str = "tls.key";
+const TLS_CA_FILE: &str = "ca.crt";
+/// Port of the Service in front of the webhooks
+const WEBHOOK_SERVICE_PORT: i32 = 443;
+
+#[derive(Snafu, Debug)]
+pub enum Error {
+    #[snafu(display("failed to read [{}]", path.display()))]
+    ReadFile {
+        source: std::io::Error,
+        path: PathBuf,
+    },
+    #[snafu(display("[{}] contains no private key", path.display()))]
+    NoPrivateKey { path: PathBuf },
+    #[snafu(display("failed to configure TLS"))]
+    ConfigureTls { source: tokio_rustls::rustls::Error },
+    #[snafu(display("failed to bind the webhook server to [{address}]"))]
+    Bind {
+        source: std::io::Error,
+        address: SocketAddr,
+    },
+    #[snafu(display("failed to configure the conversion webhook in the CRD [{crd}]"))]
+    PatchCrd {
+        source: stackable_operator::kube::Error,
+        crd: String,
+    },
+}
+
+/// Let the Kubernetes API server convert EDCClusters between their versions with the conversion
+/// webhook behind the given Service, which is trusted with the CA certificate from the given
+/// directory
+pub async fn enable_crd_conversion(
+    client: &Client,
+    service_name: &str,
+    service_namespace: &str,
+    tls_dir: &Path,
+) -> Result<(), Error> {
+    let ca_path = tls_dir.join(TLS_CA_FILE);
+    let ca_bundle = std::fs::read(&ca_path).context(ReadFileSnafu { path: &ca_path })?;
+
+    let conversion = CustomResourceConversion {
+        strategy: "Webhook".to_string(),
+        webhook: Some(WebhookConversion {
+            conversion_review_versions: vec!["v1".to_string()],
+            client_config: Some(WebhookClientConfig {
+                ca_bundle: Some(ByteString(ca_bundle)),
+                service: Some(ServiceReference {
+                    name: service_name.to_string(),
+                    namespace: service_namespace.to_string(),
+                    path: Some("/convert".to_string()),
+                    port: Some(WEBHOOK_SERVICE_PORT),
+                }),
+                url: None,
+            }),
+        }),
+    };
+
+    let crd_name = EDCCluster::crd_name();
+    Api::<CustomResourceDefinition>::all(client.as_kube_client())
+        .patch(
+            crd_name,
+            &PatchParams::default(),
+            &Patch::Merge(serde_json::json!({ "spec": { "conversion": conversion } })),
+        )
+        .await
+        .context(PatchCrdSnafu { crd: crd_name })?;
+    tracing::info!(crd = crd_name, "Enabled the conversion webhook");
+
+    Ok(())
+}
+
+/// Webhook server which is bound to its address, connections are queued until it serves them
+pub struct WebhookServer {
+    listener: TcpListener,
+    acceptor: TlsAcceptor,
+}
+
+/// Bind the webhook server to the given address with the certificate and private key from the
+/// given directory
+///
+/// The server is bound before the conversion webhook is enabled, so that the Kubernetes API
+/// server does not convert EDCClusters with a webhook which cannot be reached.
+pub async fn bind(address: SocketAddr, tls_dir: &Path) -> Result<WebhookServer, Error> {
+    let acceptor = TlsAcceptor::from(Arc::new(tls_config(tls_dir)?));
+    let listener = TcpListener::bind(address)
+        .await
+        .context(BindSnafu { address })?;
+    tracing::info!(%address, "Listening for webhook requests");
+
+    Ok(WebhookServer { listener, acceptor })
+}
+
+impl WebhookServer {
+    /// Serve the admission webhook under `/validate` and the conversion webhook under `/convert`
+    /// until the task is aborted
+    pub async fn serve(self) {
+        let WebhookServer { listener, acceptor } = self;
+
+        loop {
+            let stream = match listener.accept().await {
+                Ok((stream, _)) => stream,
+                Err(error) => {
+                    tracing::warn!(
+                        error = &error as &dyn std::error::Error,
+                        "Failed to accept a connection to the webhook server",
+                    );
+                    continue;
+                }
+            };
+
+            let acceptor = acceptor.clone();
+            tokio::spawn(async move {
+                let stream = match acceptor.accept(stream).await {
+                    Ok(stream) => stream,
+                    Err(error) => {
+                        tracing::debug!(
+                            error = &error as &dyn std::error::Error,
+                            "Failed to establish a TLS connection to the webhook server",
+                        );
+                        return;
+                    }
+                };
+                let service = service_fn(|request| async move {
+                    Ok::<_, Infallible>(handle_request(request).await)
+                });
+                if let Err(error) = http1::Builder::new()
+                    .serve_connection(TokioIo::new(stream), service)
+                    .await
+                {
+                    tracing::debug!(
+                        error = &error as &dyn std::error::Error,
+                        "Failed to serve a connection to the webhook server",
+                    );
+                }
+            });
+        }
+    }
+}
+
+fn tls_config(tls_dir: &Path) -> Result<ServerConfig, Error> {
+    let cert_path = tls_dir.join(TLS_CERT_FILE);
+    let certs = rustls_pemfile::certs(&mut open(&cert_path)?)
+        .collect::<Result<Vec<_>, _>>()
+        .context(ReadFileSnafu { path: &cert_path })?;
+
+    let key_path = tls_dir.join(TLS_KEY_FILE);
+    let key = rustls_pemfile::private_key(&mut open(&key_path)?)
+        .context(ReadFileSnafu { path: &key_path })?
+        .context(NoPrivateKeySnafu { path: &key_path })?;
+
+    ServerConfig::builder()
+        .with_no_client_auth()
+        .with_single_cert(certs, key)
+        .context(ConfigureTlsSnafu)
+}
+
+fn open(path: &Path) -> Result<BufReader<File>, Error> {
+    File::open(path)
+        .map(BufReader::new)
+        .context(ReadFileSnafu { path })
+}
+
+async fn handle_request(request: Request<Incoming>) -> Response<Full<Bytes>> {
+    if request.method() != Method::POST {
+        return status_response(StatusCode::METHOD_NOT_ALLOWED);
+    }
+    let path = request.uri().path().to_string();
+    let body = match request.into_body().collect().await {
+        Ok(body) => body.to_bytes(),
+        Err(error) => {
+            tracing::debug!(
+                error = &error as &dyn std::error::Error,
+                "Failed to read the webhook request",
+            );
+            return status_response(StatusCode::BAD_REQUEST);
+        }
+    };
+
+    let response = match path.as_str() {
+        "/validate" => handle_admission_review(&body),
+        "/convert" => handle_conversion_review(&body),
+        _ => return status_response(StatusCode::NOT_FOUND),
+    };
+
+    match response {
+        Ok(response) => Response::builder()
+            .header(CONTENT_TYPE, "application/json")
+            .body(Full::new(Bytes::from(response)))
+            .expect("the response is valid"),
+        Err(error) => {
+            tracing::debug!(
+                error = &error as &dyn std::error::Error,
+                "Failed to parse the webhook request",
+            );
+            status_response(StatusCode::BAD_REQUEST)
+        }
+    }
+}
+
+fn status_response(status: StatusCode) -> Response<Full<Bytes>> {
+    Response::builder()
+        .status(status)
+        .body(Full::new(Bytes::new()))
+        .expect("the response is valid")
+}
+
+fn handle_admission_review(body: &[u8]) -> Result<Vec<u8>, serde_json::Error> {
+    let review = serde_json::from_slice::<AdmissionReview<DynamicObject>>(body)?;
+    let response = match AdmissionRequest::try_from(review) {
+        Ok(request) => admit(&request),
+        Err(error) => AdmissionResponse::invalid(error.to_string()),
+    };
+
+    serde_json::to_vec(&response.into_review())
+}
+
+/// Decide whether the EDCCluster in the request is admitted
+fn admit(request: &AdmissionRequest<DynamicObject>) -> AdmissionResponse {
+    let mut response = AdmissionResponse::from(request);
+    // Deleted objects are not validated
+    let Some(object) = &request.object else {
+        return response;
+    };
+
+    let edc = match serde_json::to_value(object) {
+        Ok(value) => {
+            if let Err(error) = validation::validate_listener_class(&value) {
+                return response.deny(field_message(&error));
+            }
+            serde_json::from_value::<EDCCluster>(value)
+        }
+        Err(error) => Err(error),
+    };
+    let edc = match edc {
+        Ok(edc) => edc,
+        Err(error) => return response.deny(format!("failed to parse the EDCCluster: {error}")),
+    };
+
+    let errors = validation::validate(&edc);
+    if !errors.is_empty() {
+        return response.deny(
+            errors
+                .iter()
+                .map(field_message)
+                .collect::<Vec<_>>()
+                .join(", "),
+        );
+    }
+
+    let warnings = validation::warnings(&edc);
+    if !warnings.is_empty() {
+        response.warnings = Some(warnings);
+    }
+    response
+}
+
+fn field_message(error: &validation::Error) -> String {
+    format!("{}: {error}", error.field())
+}
+
+fn handle_conversion_review(body: &[u8]) -> Result<Vec<u8>, serde_json::Error> {
+    let review = serde_json::from_slice::<ConversionReview>(body)?;
+    let response = match ConversionRequest::from_review(review) {
+        Ok(request) => convert(request),
+        Err(error) => ConversionResponse::invalid(Status::failure(
+            &error.to_string(),
+            "InvalidConversionReview",
+        )),
+    };
+
+    serde_json::to_vec(&response.into_review())
+}
+
+/// Convert all objects in the request to the desired version
+fn convert(mut request: ConversionRequest) -> ConversionResponse {
+    let objects = std::mem::take(&mut request.objects);
+    let desired_api_version = request.desired_api_version.clone();
+    let response = ConversionResponse::for_request(request);
+
+    match objects
+        .into_iter()
+        .map(|object| v1alpha2::convert(object, &desired_api_version))
+        .collect::<Result<Vec<_>, _>>()
+    {
+        Ok(converted_objects) => response.success(converted_objects),
+        Err(error) => response.failure(Status::failure(
+            &snafu::Report::from_error(error).to_string(),
+            "ConversionFailed",
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use super::*;
+    use crate::test_fixtures::edc_cluster_manifest;
+
+    /// Admission review of the EDCCluster from [`edc_cluster_manifest`]
+    fn review(spec: &str, connectors: &str) -> Vec<u8> {
+        let object = serde_json::to_value(edc_cluster_manifest(spec, connectors)).unwrap();
+        serde_json::to_vec(&json!({
+            "apiVersion": "admission.k8s.io/v1",
+            "kind": "AdmissionReview",
+            "request": {
+                "uid": "705ab4f5-6393-11e8-b7cc-42010a800002",
+                "kind": {"group": "edc.stackable.tech", "version": "v1alpha1", "kind": "EDCCluster"},
+                "resource": {"group": "edc.stackable.tech", "version": "v1alpha1", "resource": "edcclusters"},
+                "name": "provider",
+                "namespace": "default",
+                "operation": "CREATE",
+                "userInfo": {"username": "admin"},
+                "object": object,
+                "dryRun": false
+            }
+        }))
+        .unwrap()
+    }
+
+    fn admission_response(spec: &str, connectors: &str) -> Value {
+        let response =
+            handle_admission_review(&review(spec, connectors)).expect("valid admission review");
+        serde_json::from_slice::<Value>(&response).unwrap()["response"].clone()
+    }
+
+    #[test]
+    fn test_admit_valid_cluster() {
+        let response = admission_response("", "roleGroups: {default: {replicas: 2}}");
+
+        assert_eq!(json!(true), response["allowed"]);
+        assert_eq!(1, response["warnings"].as_array().unwrap().len());
+    }
+
+    #[test]
+    fn test_deny_invalid_cluster() {
+        let response = admission_response("clusterConfig: {certSecret: ''}", "");
+
+        assert_eq!(json!(false), response["allowed"]);
+        assert_eq!(
+            json!(
+                "spec.connectors: the connectors role is required, \
+                spec.clusterConfig.certSecret: the name of the Secret with the connector certificate must not be empty"
+            ),
+            response["status"]["message"]
+        );
+    }
+
+    #[test]
+    fn test_convert() {
+        let review = serde_json::to_vec(&json!({
+            "apiVersion": "apiextensions.k8s.io/v1",
+            "kind": "ConversionReview",
+            "request": {
+                "uid": "705ab4f5-6393-11e8-b7cc-42010a800002",
+                "desiredAPIVersion": "edc.stackable.tech/v1alpha2",
+                "objects": [edc_cluster_manifest("clusterConfig: {listenerClass: external-unstable}", "")]
+            }
+        }))
+        .unwrap();
+
+        let response = handle_conversion_review(&review).expect("valid conversion review");
+        let response = serde_json::from_slice::<Value>(&response).unwrap()["response"].clone();
+
+        assert_eq!(json!("Success"), response["result"]["status"]);
+        let converted = &response["convertedObjects"][0];
+        assert_eq!(
+            json!("edc.stackable.tech/v1alpha2"),
+            converted["apiVersion"]
+        );
+        assert_eq!(
+            json!("external-unstable"),
+            converted["spec"]["clusterConfig"]["endpoints"]["listenerClass"]
+        );
+    }
+
+    #[test]
+    fn test_deny_unknown_listener_class() {
+        let response = admission_response(
+            "clusterConfig: {listenerClass: external}",
+            "roleGroups: {default: {replicas: 1}}",
+        );
+
+        assert_eq!(json!(false), response["allowed"]);
+        assert!(response["status"]["message"]
+            .as_str()
+            .unwrap()
+            .starts_with("spec.clusterConfig.listenerClass: unknown listener class [external]"));
+    }
+}